@@ -1,25 +1,45 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::body::MessageBody;
+use actix_web::dev::{fn_service, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::{from_fn, Next};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use actix_cors::Cors;
-use std::path::Path;
+use actix_files::{Files, NamedFile};
+use actix_multipart::{Field, Multipart};
+use futures_util::TryStreamExt;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use tokio::io::AsyncWriteExt;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::sync::Mutex;
 use std::thread;
 use std::net::SocketAddr;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::database::{self, ImageRecord};
+use crate::database::{self, ImageMetadata, ImageRecord, ListOptions, SortKey, SortOrder};
+use crate::auth;
 use crate::commands;
+use crate::image::{mime_needs_browser_conversion, SourceFormat};
+use crate::integrity::{self, RepairAction};
+use crate::pairing;
+use crate::rendition::{self, RenditionParams};
+use crate::resumable;
+use crate::search::{self, DimensionFilter, Orientation};
+use crate::thumbnail;
+use crate::trash;
+use crate::upload;
 
 // 全局服务器句柄
 static SERVER_HANDLE: Mutex<Option<ServerHandle>> = Mutex::new(None);
 
 #[derive(Debug, Serialize, Deserialize)]
-struct NetworkInfo {
-    ip_address: String,
-    port: u16,
-    url: String,
-    all_addresses: Vec<String>,
-    hostname: String,
+pub struct NetworkInfo {
+    pub ip_address: String,
+    pub port: u16,
+    pub url: String,
+    pub all_addresses: Vec<String>,
+    pub hostname: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,6 +49,82 @@ struct HealthResponse {
     version: String,
 }
 
+/// 单页最多返回的图片数量
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// 图片列表查询参数，不指定 limit 时返回全部结果
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    search: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    cursor: Option<String>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
+    /// 尺寸筛选
+    min_width: Option<u32>,
+    min_height: Option<u32>,
+    orientation: Option<Orientation>,
+}
+
+/// 单个上传文件的处理结果
+#[derive(Debug, Serialize)]
+struct UploadFileResponse {
+    filename: String,
+    #[serde(flatten)]
+    result: commands::UploadResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateUploadRequest {
+    filename: String,
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PairRequest {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsRequest {
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkTagsRequest {
+    ids: Vec<i32>,
+    #[serde(default)]
+    add: Vec<String>,
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreRequest {
+    ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyQuery {
+    check_hashes: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepairRequest {
+    actions: Vec<RepairAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameTagRequest {
+    name: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ImageResponse {
     id: i32,
@@ -38,6 +134,21 @@ struct ImageResponse {
     thumbnail_path: Option<String>,
     description: Option<String>,
     created_at: String,
+    mime_type: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    aspect_ratio: Option<f64>,
+    /// RAW+JPEG 配对中另一张图片的 ID
+    paired_id: Option<i32>,
+    /// 移入回收站的时间
+    deleted_at: Option<String>,
+    /// 最近一次哈希校验的时间和结果
+    verified_at: Option<String>,
+    verify_status: Option<String>,
+    metadata: Option<ImageMetadata>,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
 }
 
 impl From<ImageRecord> for ImageResponse {
@@ -50,6 +161,17 @@ impl From<ImageRecord> for ImageResponse {
             thumbnail_path: record.thumbnail_path,
             description: record.description,
             created_at: record.created_at,
+            mime_type: record.mime_type,
+            width: record.width,
+            height: record.height,
+            aspect_ratio: record.aspect_ratio,
+            paired_id: record.paired_id,
+            deleted_at: record.deleted_at,
+            verified_at: record.verified_at,
+            verify_status: record.verify_status,
+            metadata: record.metadata,
+            tags: record.tags,
+            snippet: record.snippet,
         }
     }
 }
@@ -67,12 +189,20 @@ pub fn start_server() -> Result<String, String> {
         return Ok("服务器已在运行中".to_string());
     }
 
-    // 注意：在生产环境中，前端由 Tauri webview 加载，不需要 dist 目录
-    // API 服务器只提供 API 接口供局域网设备访问
+    // 桌面端前端由 Tauri webview 加载；局域网设备通过本服务器访问网页端
+    let web_dist = web_dist_dir();
+    if web_dist.is_none() {
+        eprintln!("未找到网页端静态文件（dist），局域网设备只能访问 API");
+    }
 
     // 创建运行时
     let rt = tokio::runtime::Runtime::new().map_err(|e| format!("创建运行时失败: {}", e))?;
 
+    // 清理上次运行遗留的过期上传
+    if let Err(e) = resumable::cleanup_stale_uploads() {
+        eprintln!("清理过期上传失败: {}", e);
+    }
+
     // 创建 shutdown channel
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
 
@@ -83,22 +213,51 @@ pub fn start_server() -> Result<String, String> {
             let port = 3000;
             let bind_addr = format!("0.0.0.0:{}", port);
 
-            // 构建 Actix Web 服务器（仅 API 接口，不服务前端静态文件）
-            let http_server = HttpServer::new(|| {
+            // 构建 Actix Web 服务器：/api 下为接口，其余路径为网页端
+            let http_server = HttpServer::new(move || {
+                let web_dist = web_dist.clone();
                 App::new()
                     .wrap(Cors::permissive())
                     .service(
                         web::scope("/api")
+                            .wrap(from_fn(require_auth))
                             .route("/health", web::get().to(health_check))
                             .route("/network", web::get().to(get_network_info))
-                            .route("/images", web::get().to(get_all_images))
-                            .route("/images/search", web::get().to(search_images))
+                            .route("/login", web::post().to(login))
+                            .route("/pair", web::post().to(pair_device))
+                            .route("/media-token", web::post().to(create_media_token))
+                            .route("/images", web::get().to(list_images))
+                            .route("/images", web::post().to(upload_images))
+                            .route("/images/search", web::get().to(list_images))
                             .route("/images/{id}", web::get().to(get_image))
                             .route("/images/{id}/file", web::get().to(get_image_file))
                             .route("/images/{id}/thumbnail", web::get().to(get_image_thumbnail))
                             .route("/images/{id}", web::put().to(update_image))
                             .route("/images/{id}", web::delete().to(delete_image))
+                            .route("/images/{id}/tags", web::get().to(get_image_tags))
+                            .route("/images/{id}/tags", web::post().to(add_image_tags))
+                            .route("/images/{id}/tags/{tag}", web::delete().to(remove_image_tag))
+                            .route("/images/{id}/verifications", web::get().to(get_verification_history))
+                            .route("/uploads", web::post().to(create_upload))
+                            .route("/uploads/{id}", web::get().to(get_upload))
+                            .route("/uploads/{id}", web::patch().to(append_upload_chunk))
+                            .route("/uploads/{id}", web::delete().to(cancel_upload))
+                            .route("/uploads/{id}/finalize", web::post().to(finalize_upload))
+                            .route("/trash", web::get().to(list_trash))
+                            .route("/trash", web::delete().to(empty_trash))
+                            .route("/trash/restore", web::post().to(restore_images))
+                            .route("/library/verify", web::get().to(verify_library))
+                            .route("/library/repair", web::post().to(repair_library))
+                            .route("/tags", web::get().to(list_tags))
+                            .route("/tags/bulk", web::post().to(bulk_update_tags))
+                            .route("/tags/{tag}", web::put().to(rename_tag))
+                            .route("/tags/{tag}/images", web::get().to(get_images_by_tag))
                     )
+                    .configure(move |cfg| {
+                        if let Some(dir) = web_dist {
+                            cfg.service(web_app_files(dir));
+                        }
+                    })
                     // 404 处理
                     .default_service(web::route().to(not_found))
             })
@@ -136,6 +295,91 @@ pub fn start_server() -> Result<String, String> {
     Ok("服务器启动成功".to_string())
 }
 
+/// 网页端静态文件目录
+///
+/// 打包后由 copy-assets 复制到可执行文件旁；开发模式下可执行文件在 src-tauri/target/debug 中，
+/// 向上查找项目根目录的 dist。
+fn web_dist_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    exe.ancestors()
+        .skip(1)
+        .take(4)
+        .map(|dir| dir.join("dist"))
+        .find(|dir| dir.join("index.html").is_file())
+}
+
+/// 网页端静态文件服务，找不到的路径返回 index.html，由前端路由处理（如扫码打开的 /pair）
+fn web_app_files(dir: PathBuf) -> Files {
+    let index = dir.join("index.html");
+    Files::new("/", dir)
+        .index_file("index.html")
+        .default_handler(fn_service(move |req: ServiceRequest| {
+            let index = index.clone();
+            async move {
+                let (req, _) = req.into_parts();
+                let file = NamedFile::open_async(index).await?;
+                let res = file.into_response(&req);
+                Ok(ServiceResponse::new(req, res))
+            }
+        }))
+}
+
+/// 不需要登录即可访问的接口
+const PUBLIC_PATHS: [&str; 3] = ["/api/health", "/api/login", "/api/pair"];
+
+/// 是否为读取图片文件或缩略图的请求（GET /api/images/{id}/file、/thumbnail）
+///
+/// 只有这些接口接受查询参数中的媒体令牌，供 <img> 等无法设置请求头的场景使用。
+fn is_media_request(req: &ServiceRequest) -> bool {
+    req.method() == Method::GET
+        && req
+            .path()
+            .strip_prefix("/api/images/")
+            .and_then(|rest| rest.split_once('/'))
+            .is_some_and(|(id, tail)| !id.is_empty() && matches!(tail, "file" | "thumbnail"))
+}
+
+/// 从请求中取出令牌：Authorization 头，媒体请求还可以使用 token 查询参数
+fn extract_token(req: &ServiceRequest, media_request: bool) -> Option<String> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        return value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+    }
+
+    if !media_request {
+        return None;
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().remove("token"))
+}
+
+/// 认证中间件：除公开接口外，所有 /api 请求都需要有效令牌，媒体令牌只能访问图片文件和缩略图
+async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if PUBLIC_PATHS.contains(&req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let media_request = is_media_request(&req);
+    let error = match extract_token(&req, media_request).map(|token| auth::verify_token(&token)) {
+        Some(Ok(claims)) if claims.sub != auth::MEDIA_SUBJECT || media_request => {
+            return next.call(req).await.map(ServiceResponse::map_into_left_body);
+        }
+        Some(Ok(_)) => "媒体令牌只能访问图片文件和缩略图".to_string(),
+        Some(Err(e)) => e,
+        None => "未登录".to_string(),
+    };
+
+    let response = HttpResponse::Unauthorized().json(serde_json::json!({ "error": error }));
+    Ok(req.into_response(response).map_into_right_body())
+}
+
 /// 停止 API 服务器
 pub fn stop_server() -> Result<String, String> {
     let mut handle = SERVER_HANDLE.lock().unwrap();
@@ -188,7 +432,8 @@ async fn health_check() -> impl Responder {
     }).unwrap())
 }
 
-async fn get_network_info() -> impl Responder {
+/// 获取本机局域网地址信息
+pub fn network_info() -> NetworkInfo {
     let ip_address = get_local_ip();
     let port = 3000;
     let url = format!("http://{}:{}", ip_address, port);
@@ -197,20 +442,91 @@ async fn get_network_info() -> impl Responder {
     all_addresses.sort();
     all_addresses.dedup();
 
-    HttpResponse::Ok().body(serde_json::to_string(&NetworkInfo {
+    NetworkInfo {
         ip_address,
         port,
         url,
         all_addresses,
         hostname: get_hostname(),
-    }).unwrap())
+    }
 }
 
-async fn get_all_images() -> impl Responder {
-    match database::get_all_images() {
-        Ok(records) => {
-            let images: Vec<ImageResponse> = records.into_iter().map(Into::into).collect();
-            HttpResponse::Ok().json(serde_json::json!({ "images": images }))
+async fn get_network_info() -> impl Responder {
+    HttpResponse::Ok().body(serde_json::to_string(&network_info()).unwrap())
+}
+
+async fn login(req: HttpRequest, body: web::Json<LoginRequest>) -> impl Responder {
+    let password = body.into_inner().password;
+    let client = req
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+
+    match web::block(move || {
+        if auth::password_change_required() {
+            return Err("请先在桌面应用中修改默认密码".to_string());
+        }
+        auth::login(&password, &client)
+    })
+    .await
+    {
+        Ok(Ok(response)) => HttpResponse::Ok().json(response),
+        Ok(Err(e)) => HttpResponse::Unauthorized().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("登录失败: {}", e)
+        })),
+    }
+}
+
+/// 签发访问图片文件和缩略图的短期令牌（放在 token 查询参数中使用）
+async fn create_media_token() -> impl Responder {
+    match auth::issue_media_token() {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+    }
+}
+
+/// 手机端用扫码得到的配对令牌换取登录令牌
+async fn pair_device(body: web::Json<PairRequest>) -> impl Responder {
+    match pairing::redeem_pairing_token(&body.token) {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::Unauthorized().json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn list_images(web::Query(query): web::Query<ListQuery>) -> impl Responder {
+    let mut search_query = match search::parse_query(query.search.as_deref().unwrap_or("")) {
+        Ok(q) => q,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+        }
+    };
+    DimensionFilter {
+        min_width: query.min_width,
+        min_height: query.min_height,
+        orientation: query.orientation,
+    }
+    .apply(&mut search_query);
+
+    let options = ListOptions {
+        limit: query.limit.map(|limit| limit.min(MAX_PAGE_SIZE)),
+        offset: query.offset,
+        cursor: query.cursor,
+        sort: query.sort,
+        order: query.order.unwrap_or_default(),
+    };
+    if let Err(e) = database::validate_list_options(&search_query, &options) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+    }
+
+    match database::query_images(&search_query, &options) {
+        Ok(page) => {
+            let images: Vec<ImageResponse> = page.images.into_iter().map(Into::into).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "images": images,
+                "total": page.total,
+                "next_cursor": page.next_cursor,
+            }))
         }
         Err(e) => {
             HttpResponse::InternalServerError().json(serde_json::json!({
@@ -220,33 +536,301 @@ async fn get_all_images() -> impl Responder {
     }
 }
 
-async fn search_images(web::Query(query): web::Query<HashMap<String, String>>) -> impl Responder {
-    let search_term = query.get("search").map(|s| s.as_str()).unwrap_or("");
+/// 上传图片（multipart/form-data，可一次上传多个文件）
+///
+/// 每个文件先写入临时文件，再走与本地导入相同的哈希去重和存储流程。
+async fn upload_images(mut payload: Multipart) -> impl Responder {
+    let temp_dir = upload::get_upload_temp_dir();
+    if let Err(e) = tokio::fs::create_dir_all(&temp_dir).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("创建上传临时目录失败: {}", e)
+        }));
+    }
 
-    if search_term.trim().is_empty() {
-        match database::get_all_images() {
-            Ok(records) => {
-                let images: Vec<ImageResponse> = records.into_iter().map(Into::into).collect();
-                HttpResponse::Ok().json(serde_json::json!({ "images": images }))
-            }
+    let mut results = Vec::new();
+    // 单个请求的上传总量与断点续传的单文件上限相同，更大的文件应使用断点续传
+    let mut received: u64 = 0;
+    loop {
+        let mut field = match payload.try_next().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
             Err(e) => {
-                HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("查询图片失败: {}", e)
-                }))
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("解析上传数据失败: {}", e),
+                    "results": results,
+                }));
             }
-        }
-    } else {
-        match database::search_images(search_term) {
-            Ok(records) => {
-                let images: Vec<ImageResponse> = records.into_iter().map(Into::into).collect();
-                HttpResponse::Ok().json(serde_json::json!({ "images": images }))
+        };
+
+        // 跳过非文件字段；文件名只保留最后一段，忽略客户端传来的路径
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .and_then(|name| Path::new(name).file_name())
+            .map(|name| name.to_string_lossy().to_string());
+        let Some(filename) = filename else {
+            continue;
+        };
+
+        let limit = resumable::MAX_UPLOAD_SIZE - received;
+        let result = match save_upload_field(&mut field, &temp_dir, limit).await {
+            Ok(None) => {
+                return HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                    "error": format!(
+                        "上传内容超过 {} 字节，更大的文件请使用断点续传",
+                        resumable::MAX_UPLOAD_SIZE
+                    ),
+                    "results": results,
+                }));
+            }
+            Ok(Some((temp_path, size))) => {
+                received += size;
+                let name = filename.clone();
+                web::block(move || {
+                    let result = upload::import_image_file(&temp_path, &name);
+                    let _ = fs::remove_file(&temp_path);
+                    result
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("导入任务失败: {}", e)))
+            }
+            Err(e) => Err(e),
+        };
+
+        results.push(UploadFileResponse {
+            filename,
+            result: match result {
+                Ok(result) => result.into(),
+                Err(message) => commands::UploadResult {
+                    success: false,
+                    message,
+                    image_id: None,
+                    file_size: None,
+                },
+            },
+        });
+    }
+
+    if results.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "没有上传文件" }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({ "results": results }))
+}
+
+/// 将上传字段的内容流式写入临时文件，返回临时文件路径和大小
+///
+/// 内容超过 limit 字节时停止接收、删除临时文件并返回 None。
+async fn save_upload_field(
+    field: &mut Field,
+    temp_dir: &Path,
+    limit: u64,
+) -> Result<Option<(PathBuf, u64)>, String> {
+    let mut name = [0u8; 16];
+    OsRng.fill_bytes(&mut name);
+    let temp_path = temp_dir.join(format!("{}.part", hex::encode(name)));
+
+    let mut file = tokio::fs::File::create(&temp_path)
+        .await
+        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+
+    let mut size: u64 = 0;
+    let written: Result<bool, String> = async {
+        while let Some(chunk) = field
+            .try_next()
+            .await
+            .map_err(|e| format!("接收上传数据失败: {}", e))?
+        {
+            size += chunk.len() as u64;
+            if size > limit {
+                return Ok(false);
             }
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("写入临时文件失败: {}", e))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| format!("写入临时文件失败: {}", e))?;
+        Ok(true)
+    }
+    .await;
+
+    match written {
+        Ok(true) => Ok(Some((temp_path, size))),
+        Ok(false) => {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            Ok(None)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            Err(e)
+        }
+    }
+}
+
+/// 断点续传：当前偏移量请求/响应头
+const UPLOAD_OFFSET_HEADER: &str = "Upload-Offset";
+
+/// 断点续传：创建上传
+async fn create_upload(body: web::Json<CreateUploadRequest>) -> impl Responder {
+    let body = body.into_inner();
+
+    match web::block(move || resumable::create_upload(&body.filename, body.size)).await {
+        Ok(Ok(session)) => HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/api/uploads/{}", session.id)))
+            .insert_header((UPLOAD_OFFSET_HEADER, session.offset.to_string()))
+            .json(session),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("创建上传失败: {}", e)
+        })),
+    }
+}
+
+/// 断点续传：查询已接收的字节数
+async fn get_upload(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+
+    match web::block(move || resumable::get_upload(&id)).await {
+        Ok(Ok(session)) => HttpResponse::Ok()
+            .insert_header((UPLOAD_OFFSET_HEADER, session.offset.to_string()))
+            .json(session),
+        Ok(Err(e)) => HttpResponse::NotFound().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("查询上传失败: {}", e)
+        })),
+    }
+}
+
+/// 断点续传：追加数据块
+///
+/// 请求头 Upload-Offset 必须等于服务器已接收的字节数，否则返回 409 和当前偏移量，
+/// 客户端据此从正确位置继续上传。
+async fn append_upload_chunk(
+    req: HttpRequest,
+    path: web::Path<String>,
+    mut payload: web::Payload,
+) -> impl Responder {
+    let id = path.into_inner();
+
+    let Some(offset) = req
+        .headers()
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+    else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "缺少或无效的 Upload-Offset 请求头"
+        }));
+    };
+
+    let session = {
+        let id = id.clone();
+        match web::block(move || resumable::get_upload(&id)).await {
+            Ok(Ok(session)) => session,
+            Ok(Err(e)) => return HttpResponse::NotFound().json(serde_json::json!({ "error": e })),
             Err(e) => {
-                HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("搜索图片失败: {}", e)
-                }))
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("查询上传失败: {}", e)
+                }));
             }
         }
+    };
+
+    if !resumable::begin_write(&id) {
+        return HttpResponse::Conflict().json(serde_json::json!({ "error": "该上传正在写入数据" }));
+    }
+    let result = write_upload_chunk(&session, offset, &mut payload).await;
+    resumable::end_write(&id);
+
+    match result {
+        Ok(offset) => HttpResponse::Ok()
+            .insert_header((UPLOAD_OFFSET_HEADER, offset.to_string()))
+            .json(serde_json::json!({ "offset": offset, "size": session.size })),
+        Err(response) => response,
+    }
+}
+
+/// 将请求体追加到上传临时文件，返回新的偏移量
+async fn write_upload_chunk(
+    session: &resumable::UploadSession,
+    offset: u64,
+    payload: &mut web::Payload,
+) -> Result<u64, HttpResponse> {
+    let internal_error = |e: String| {
+        HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
+    };
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(resumable::data_path(&session.id))
+        .await
+        .map_err(|e| internal_error(format!("打开临时文件失败: {}", e)))?;
+
+    // 以文件实际大小为准，处理并发或上次中断的情况
+    let current = file
+        .metadata()
+        .await
+        .map_err(|e| internal_error(format!("读取临时文件失败: {}", e)))?
+        .len();
+    if offset != current {
+        return Err(HttpResponse::Conflict()
+            .insert_header((UPLOAD_OFFSET_HEADER, current.to_string()))
+            .json(serde_json::json!({ "error": "偏移量不匹配", "offset": current })));
+    }
+
+    let mut written = current;
+    while let Some(chunk) = payload.try_next().await.map_err(|e| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("接收上传数据失败: {}", e)
+        }))
+    })? {
+        if written + chunk.len() as u64 > session.size {
+            // 超出声明的文件大小：丢弃本次请求写入的数据
+            let _ = file.set_len(current).await;
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "数据超出文件大小",
+                "offset": current,
+            })));
+        }
+
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| internal_error(format!("写入临时文件失败: {}", e)))?;
+        written += chunk.len() as u64;
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| internal_error(format!("写入临时文件失败: {}", e)))?;
+
+    Ok(written)
+}
+
+/// 断点续传：数据接收完整后导入图库
+async fn finalize_upload(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+
+    match web::block(move || resumable::finalize_upload(&id)).await {
+        Ok(Ok(result)) => HttpResponse::Ok().json(commands::UploadResult::from(result)),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("导入任务失败: {}", e)
+        })),
+    }
+}
+
+/// 断点续传：取消上传并删除临时文件
+async fn cancel_upload(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+
+    match web::block(move || resumable::cancel_upload(&id)).await {
+        Ok(Ok(())) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("取消上传失败: {}", e)
+        })),
     }
 }
 
@@ -264,6 +848,17 @@ async fn get_image(path: web::Path<i32>) -> impl Responder {
                 thumbnail_path: image_info.thumbnail_path,
                 description: image_info.description,
                 created_at: image_info.created_at,
+                mime_type: image_info.mime_type,
+                width: image_info.width,
+                height: image_info.height,
+                aspect_ratio: image_info.aspect_ratio,
+                paired_id: image_info.paired_id,
+                deleted_at: image_info.deleted_at,
+                verified_at: image_info.verified_at,
+                verify_status: image_info.verify_status,
+                metadata: image_info.metadata,
+                tags: image_info.tags,
+                snippet: image_info.snippet,
             };
             HttpResponse::Ok().body(serde_json::to_string(&response).unwrap())
         }
@@ -275,72 +870,109 @@ async fn get_image(path: web::Path<i32>) -> impl Responder {
     }
 }
 
-async fn get_image_file(path: web::Path<i32>) -> impl Responder {
+async fn get_image_file(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    web::Query(mut params): web::Query<RenditionParams>,
+) -> impl Responder {
     let id = path.into_inner();
 
-    match commands::get_image_by_id(id) {
-        Ok(image) => {
-            let file_path = Path::new(&image.path);
-
-            if !file_path.exists() {
-                return HttpResponse::NotFound().body("文件不存在");
+    // 未指定缩放参数时直接返回原图
+    if params.is_empty() {
+        let image = match commands::get_image_by_id(id) {
+            Ok(image) => image,
+            Err(e) => {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "error": format!("图片不存在: {}", e)
+                }));
             }
-
-            // 读取文件
-            match fs::read(&file_path) {
-                Ok(data) => {
-                    // 确定 MIME 类型
-                    let mime_type_str = mime_guess::from_path(&file_path)
-                        .first()
-                        .map(|m| m.to_string())
-                        .unwrap_or_else(|| "application/octet-stream".to_string());
-
-                    HttpResponse::Ok()
-                        .content_type(mime_type_str.as_str())
-                        .insert_header(("Cache-Control", "public, max-age=86400"))
-                        .body(data)
-                }
-                Err(e) => {
-                    HttpResponse::InternalServerError().body(format!("读取文件失败: {}", e))
-                }
+        };
+
+        let mime_type = image.mime_type.as_deref().unwrap_or("");
+        let needs_conversion = mime_needs_browser_conversion(mime_type);
+        if !needs_conversion || accepts_mime(&req, mime_type) {
+            let mut response = serve_file(
+                Path::new(&image.path),
+                image.mime_type.as_deref(),
+                "public, max-age=86400",
+            );
+            if needs_conversion {
+                response
+                    .headers_mut()
+                    .insert(header::VARY, header::HeaderValue::from_static("Accept"));
             }
+            return response;
         }
+
+        // 浏览器无法显示 HEIC、RAW 时返回原尺寸的 JPEG
+        params = RenditionParams {
+            format: Some("jpeg".to_string()),
+            ..Default::default()
+        };
+    }
+
+    let spec = match params.parse() {
+        Ok(spec) => spec,
         Err(e) => {
-            HttpResponse::NotFound().json(serde_json::json!({
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    let record = match database::get_image_by_id(id) {
+        Ok(record) => record,
+        Err(e) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
                 "error": format!("图片不存在: {}", e)
+            }));
+        }
+    };
+
+    match web::block(move || rendition::get_or_create_rendition(&record, &spec)).await {
+        Ok(Ok(rendition_path)) => serve_file(&rendition_path, None, "public, max-age=86400"),
+        Ok(Err(e)) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("生成缩放图失败: {}", e)
             }))
         }
+        Err(e) => {
+            HttpResponse::InternalServerError().body(format!("生成缩放图失败: {}", e))
+        }
     }
 }
 
+/// 请求方是否在 Accept 中声明能直接显示该格式
+fn accepts_mime(req: &HttpRequest, mime_type: &str) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.split(',').any(|item| item.trim().starts_with(mime_type)))
+}
+
 async fn get_image_thumbnail(path: web::Path<i32>) -> impl Responder {
     let id = path.into_inner();
 
-    match commands::get_image_by_id(id) {
-        Ok(image) => {
-            let file_path = Path::new(&image.path);
+    let record = match database::get_image_by_id(id) {
+        Ok(record) => record,
+        Err(e) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("图片不存在: {}", e)
+            }));
+        }
+    };
 
-            if !file_path.exists() {
-                return HttpResponse::NotFound().body("文件不存在");
-            }
+    let original_path = record.path.clone();
+    let original_mime_type = record.mime_type.clone();
 
-            // 读取文件
-            match fs::read(&file_path) {
-                Ok(data) => {
-                    HttpResponse::Ok()
-                        .content_type("image/jpeg")
-                        .insert_header(("Cache-Control", "public, max-age=31536000, immutable"))
-                        .body(data)
-                }
-                Err(e) => {
-                    HttpResponse::InternalServerError().body(format!("读取文件失败: {}", e))
-                }
-            }
+    // 缩略图缺失时即时生成（解码较耗时，放到阻塞线程池执行）
+    match web::block(move || thumbnail::ensure_thumbnail(&record)).await {
+        Ok(Ok(thumb_path)) => serve_file(&thumb_path, None, "public, max-age=86400"),
+        Ok(Err(e)) => {
+            // 原图无法解码时回退为原图，且不缓存，以便之后能拿到真正的缩略图
+            eprintln!("生成缩略图失败 (id={}): {}", id, e);
+            serve_file(Path::new(&original_path), original_mime_type.as_deref(), "no-cache")
         }
         Err(e) => {
-            HttpResponse::NotFound().json(serde_json::json!({
-                "error": format!("图片不存在: {}", e)
-            }))
+            HttpResponse::InternalServerError().body(format!("生成缩略图失败: {}", e))
         }
     }
 }
@@ -381,6 +1013,165 @@ async fn delete_image(path: web::Path<i32>) -> impl Responder {
     }
 }
 
+async fn list_trash() -> impl Responder {
+    match trash::list_trash() {
+        Ok(images) => {
+            let images: Vec<ImageResponse> = images.into_iter().map(Into::into).collect();
+            HttpResponse::Ok().json(serde_json::json!({ "images": images }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+    }
+}
+
+async fn restore_images(body: web::Json<RestoreRequest>) -> impl Responder {
+    let ids = body.into_inner().ids;
+
+    match web::block(move || trash::restore_images(&ids)).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("恢复图片失败: {}", e)
+        })),
+    }
+}
+
+async fn empty_trash() -> impl Responder {
+    match web::block(trash::empty_trash).await {
+        Ok(Ok(purged)) => HttpResponse::Ok().json(serde_json::json!({ "purged": purged })),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("清空回收站失败: {}", e)
+        })),
+    }
+}
+
+async fn verify_library(query: web::Query<VerifyQuery>) -> impl Responder {
+    let check_hashes = query.check_hashes.unwrap_or(true);
+
+    match web::block(move || integrity::verify_library(check_hashes)).await {
+        Ok(Ok(report)) => HttpResponse::Ok().json(report),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("检查图片库失败: {}", e)
+        })),
+    }
+}
+
+async fn repair_library(body: web::Json<RepairRequest>) -> impl Responder {
+    let actions = body.into_inner().actions;
+
+    match web::block(move || integrity::repair_library(&actions)).await {
+        Ok(Ok(report)) => HttpResponse::Ok().json(report),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("修复图片库失败: {}", e)
+        })),
+    }
+}
+
+async fn get_image_tags(path: web::Path<i32>) -> impl Responder {
+    let id = path.into_inner();
+
+    match commands::get_image_tags(id) {
+        Ok(tags) => HttpResponse::Ok().json(serde_json::json!({ "tags": tags })),
+        Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+async fn get_verification_history(path: web::Path<i32>) -> impl Responder {
+    let id = path.into_inner();
+
+    match commands::get_verification_history(id) {
+        Ok(history) => HttpResponse::Ok().json(serde_json::json!({ "verifications": history })),
+        Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+async fn add_image_tags(path: web::Path<i32>, body: web::Json<TagsRequest>) -> impl Responder {
+    let id = path.into_inner();
+
+    match commands::add_image_tags(id, body.into_inner().tags) {
+        Ok(tags) => HttpResponse::Ok().json(serde_json::json!({ "tags": tags })),
+        Err(e) => {
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+async fn remove_image_tag(path: web::Path<(i32, String)>) -> impl Responder {
+    let (id, tag) = path.into_inner();
+
+    match commands::remove_image_tags(id, vec![tag]) {
+        Ok(tags) => HttpResponse::Ok().json(serde_json::json!({ "tags": tags })),
+        Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+async fn list_tags(web::Query(query): web::Query<HashMap<String, String>>) -> impl Responder {
+    match commands::list_tags(query.get("prefix").cloned()) {
+        Ok(tags) => HttpResponse::Ok().json(serde_json::json!({ "tags": tags })),
+        Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+async fn bulk_update_tags(body: web::Json<BulkTagsRequest>) -> impl Responder {
+    let request = body.into_inner();
+
+    let added = match commands::bulk_tag_images(request.ids.clone(), request.add) {
+        Ok(n) => n,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    let removed = match commands::bulk_untag_images(request.ids, request.remove) {
+        Ok(n) => n,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "added": added,
+        "removed": removed
+    }))
+}
+
+async fn rename_tag(path: web::Path<String>, body: web::Json<RenameTagRequest>) -> impl Responder {
+    let old_tag = path.into_inner();
+
+    match commands::rename_tag(old_tag, body.into_inner().name) {
+        Ok(updated) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "updated": updated })),
+        Err(e) => {
+            HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e }))
+        }
+    }
+}
+
+async fn get_images_by_tag(path: web::Path<String>) -> impl Responder {
+    let tag = path.into_inner();
+
+    match database::get_images_by_tag(tag.trim()) {
+        Ok(records) => {
+            let images: Vec<ImageResponse> = records.into_iter().map(Into::into).collect();
+            HttpResponse::Ok().json(serde_json::json!({ "images": images }))
+        }
+        Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("查询图片失败: {}", e)
+            }))
+        }
+    }
+}
+
 async fn not_found() -> impl Responder {
     HttpResponse::NotFound().json(serde_json::json!({
         "error": "Not Found"
@@ -389,6 +1180,35 @@ async fn not_found() -> impl Responder {
 
 // 辅助函数
 
+/// 读取文件并返回
+///
+/// content_type 为数据库中记录的 MIME 类型；没有记录时按文件内容识别，最后才按扩展名推断。
+fn serve_file(file_path: &Path, content_type: Option<&str>, cache_control: &str) -> HttpResponse {
+    if !file_path.exists() {
+        return HttpResponse::NotFound().body("文件不存在");
+    }
+
+    match fs::read(file_path) {
+        Ok(data) => {
+            let mime_type_str = match content_type {
+                Some(mime_type) => mime_type.to_string(),
+                None => SourceFormat::from_magic(&data)
+                    .map(|format| format.mime_type().to_string())
+                    .or_else(|| mime_guess::from_path(file_path).first().map(|m| m.to_string()))
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            };
+
+            HttpResponse::Ok()
+                .content_type(mime_type_str.as_str())
+                .insert_header(("Cache-Control", cache_control.to_string()))
+                .body(data)
+        }
+        Err(e) => {
+            HttpResponse::InternalServerError().body(format!("读取文件失败: {}", e))
+        }
+    }
+}
+
 fn get_local_ip() -> String {
     let addresses = get_all_local_ips();
 
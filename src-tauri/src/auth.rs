@@ -0,0 +1,387 @@
+use crate::config;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 首次运行时设置的默认密码，修改前 API 不接受登录
+const DEFAULT_PASSWORD: &str = "admin";
+
+/// 密码最短长度
+const MIN_PASSWORD_LENGTH: usize = 6;
+
+/// 媒体令牌的主体，只能访问图片文件和缩略图
+pub const MEDIA_SUBJECT: &str = "media";
+
+/// 媒体令牌有效期（秒），通过查询参数传递，尽量缩短暴露时间
+const MEDIA_TOKEN_TTL_SECS: u64 = 300;
+
+/// 连续登录失败达到该次数后暂时锁定
+const MAX_FAILED_LOGINS: u32 = 5;
+
+/// 登录锁定时长（秒）
+const LOGIN_LOCKOUT_SECS: u64 = 60;
+
+/// 令牌签名密钥，启动时从配置读取一次
+static TOKEN_SECRET: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+/// 各客户端的连续登录失败次数和锁定截止时间（Unix 秒）
+static FAILED_LOGINS: Mutex<Option<HashMap<String, (u32, u64)>>> = Mutex::new(None);
+
+/// 令牌载荷
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    /// 签发时间（Unix 秒）
+    pub iat: u64,
+    /// 过期时间（Unix 秒）
+    pub exp: u64,
+}
+
+/// 登录结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub expires_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 计算密码的 Argon2 哈希（PHC 字符串格式）
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("计算密码哈希失败: {}", e))
+}
+
+/// 首次运行时生成默认密码的 Argon2 哈希和令牌签名密钥，并缓存签名密钥
+///
+/// 应在启动时调用一次；默认密码被标记为需要修改，修改前 API 不接受登录。
+pub fn init_credentials() -> Result<(), String> {
+    let mut secret = TOKEN_SECRET.lock().unwrap();
+    let mut config = config::load_config();
+    let mut changed = false;
+
+    if config.password_hash.is_none() {
+        config.password_hash = Some(hash_password(DEFAULT_PASSWORD)?);
+        config.password_change_required = true;
+        changed = true;
+    }
+    if config.token_secret.is_none() {
+        config.token_secret = Some(generate_secret());
+        changed = true;
+    }
+    if changed {
+        config::save_config(&config)?;
+    }
+
+    *secret = Some(decode_secret(config.token_secret.as_deref().unwrap_or_default())?);
+    Ok(())
+}
+
+/// 是否仍在使用首次运行生成的默认密码
+pub fn password_change_required() -> bool {
+    config::load_config().password_change_required
+}
+
+/// 校验密码
+pub fn verify_password(password: &str) -> Result<bool, String> {
+    let config = config::load_config();
+
+    let Some(stored) = config.password_hash else {
+        return Err("尚未设置密码".to_string());
+    };
+
+    let hash = PasswordHash::new(&stored)
+        .map_err(|e| format!("配置中的密码哈希无效: {}", e))?;
+
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+/// 修改密码
+///
+/// 同时更换令牌签名密钥，使之前签发的所有令牌失效。
+pub fn change_password(current_password: &str, new_password: &str) -> Result<(), String> {
+    if !verify_password(current_password)? {
+        return Err("当前密码错误".to_string());
+    }
+
+    if new_password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("新密码至少需要 {} 个字符", MIN_PASSWORD_LENGTH));
+    }
+
+    let mut secret = TOKEN_SECRET.lock().unwrap();
+    let mut config = config::load_config();
+    let new_secret = generate_secret();
+    config.password_hash = Some(hash_password(new_password)?);
+    config.password_change_required = false;
+    config.token_secret = Some(new_secret.clone());
+    config::save_config(&config)?;
+
+    *secret = Some(decode_secret(&new_secret)?);
+    Ok(())
+}
+
+fn generate_secret() -> String {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    hex::encode(secret)
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    hex::decode(secret).map_err(|e| format!("令牌密钥无效: {}", e))
+}
+
+/// 获取缓存的令牌签名密钥，尚未初始化时先执行 init_credentials
+fn token_secret() -> Result<Vec<u8>, String> {
+    if let Some(secret) = TOKEN_SECRET.lock().unwrap().as_ref() {
+        return Ok(secret.clone());
+    }
+
+    init_credentials()?;
+    TOKEN_SECRET
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "令牌密钥未初始化".to_string())
+}
+
+fn sign(secret: &[u8], payload: &str) -> Result<HmacSha256, String> {
+    let mut mac = HmacSha256::new_from_slice(secret)
+        .map_err(|e| format!("令牌密钥无效: {}", e))?;
+    mac.update(payload.as_bytes());
+    Ok(mac)
+}
+
+/// 签发令牌：`base64url(载荷).base64url(HMAC-SHA256 签名)`
+pub fn issue_token(subject: &str) -> Result<LoginResponse, String> {
+    let ttl_hours = config::load_config().token_ttl_hours;
+    sign_token(subject, ttl_hours * 3600)
+}
+
+/// 签发只能访问图片文件和缩略图的短期令牌，供 <img> 等无法设置请求头的场景放在查询参数中
+pub fn issue_media_token() -> Result<LoginResponse, String> {
+    sign_token(MEDIA_SUBJECT, MEDIA_TOKEN_TTL_SECS)
+}
+
+fn sign_token(subject: &str, ttl_secs: u64) -> Result<LoginResponse, String> {
+    let iat = now_secs();
+    let claims = Claims {
+        sub: subject.to_string(),
+        iat,
+        exp: iat + ttl_secs,
+    };
+
+    let json = serde_json::to_vec(&claims)
+        .map_err(|e| format!("序列化令牌失败: {}", e))?;
+    let payload = URL_SAFE_NO_PAD.encode(json);
+    let signature = sign(&token_secret()?, &payload)?.finalize().into_bytes();
+
+    Ok(LoginResponse {
+        token: format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(signature)),
+        expires_at: claims.exp,
+    })
+}
+
+/// 校验令牌签名和有效期
+pub fn verify_token(token: &str) -> Result<Claims, String> {
+    let (payload, signature) = token
+        .split_once('.')
+        .ok_or_else(|| "令牌格式无效".to_string())?;
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| "令牌格式无效".to_string())?;
+
+    sign(&token_secret()?, payload)?
+        .verify_slice(&signature)
+        .map_err(|_| "令牌签名无效".to_string())?;
+
+    let json = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| "令牌格式无效".to_string())?;
+    let claims: Claims = serde_json::from_slice(&json)
+        .map_err(|_| "令牌格式无效".to_string())?;
+
+    if claims.exp <= now_secs() {
+        return Err("令牌已过期".to_string());
+    }
+
+    Ok(claims)
+}
+
+/// 登录：校验密码并签发令牌
+///
+/// client 标识登录来源（如客户端 IP），同一来源连续失败 MAX_FAILED_LOGINS 次后锁定一段时间。
+pub fn login(password: &str, client: &str) -> Result<LoginResponse, String> {
+    let now = now_secs();
+    {
+        let mut failures = FAILED_LOGINS.lock().unwrap();
+        let failures = failures.get_or_insert_with(HashMap::new);
+        failures.retain(|_, (_, locked_until)| *locked_until == 0 || *locked_until > now);
+        if let Some((_, locked_until)) = failures.get(client) {
+            if *locked_until > now {
+                return Err(format!("登录失败次数过多，请 {} 秒后再试", locked_until - now));
+            }
+        }
+    }
+
+    let valid = verify_password(password)?;
+    {
+        let mut failures = FAILED_LOGINS.lock().unwrap();
+        let failures = failures.get_or_insert_with(HashMap::new);
+        if !valid {
+            let entry = failures.entry(client.to_string()).or_insert((0, 0));
+            entry.0 += 1;
+            if entry.0 >= MAX_FAILED_LOGINS {
+                *entry = (0, now + LOGIN_LOCKOUT_SECS);
+            }
+            return Err("密码错误".to_string());
+        }
+        failures.remove(client);
+    }
+
+    issue_token("admin")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    /// 创建测试图片库并设置密码，返回图片库（持有期间配置指向临时目录）
+    fn library_with_password(password: &str) -> TestLibrary {
+        let library = TestLibrary::new();
+        init_credentials().unwrap();
+        change_password(DEFAULT_PASSWORD, password).unwrap();
+        library
+    }
+
+    #[test]
+    fn first_run_requires_changing_default_password() {
+        let _library = TestLibrary::new();
+        init_credentials().unwrap();
+
+        assert!(password_change_required());
+        assert!(verify_password(DEFAULT_PASSWORD).unwrap());
+        assert!(!verify_password("wrong").unwrap());
+
+        change_password(DEFAULT_PASSWORD, "secret-password").unwrap();
+        assert!(!password_change_required());
+        assert!(verify_password("secret-password").unwrap());
+        assert!(change_password("secret-password", "short").is_err());
+    }
+
+    #[test]
+    fn signed_tokens_verify_and_tampering_is_rejected() {
+        let _library = library_with_password("secret-password");
+
+        let response = issue_token("admin").unwrap();
+        let claims = verify_token(&response.token).unwrap();
+        assert_eq!(claims.sub, "admin");
+        assert_eq!(claims.exp, response.expires_at);
+        assert_eq!(claims.exp - claims.iat, 24 * 3600);
+
+        let (payload, signature) = response.token.split_once('.').unwrap();
+        let forged_claims = Claims {
+            sub: "admin".to_string(),
+            iat: claims.iat,
+            exp: claims.exp + 3600,
+        };
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged_claims).unwrap());
+        assert_eq!(
+            verify_token(&format!("{}.{}", forged_payload, signature)).unwrap_err(),
+            "令牌签名无效"
+        );
+
+        let mut bad_signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+        bad_signature[0] ^= 1;
+        let tampered = format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(bad_signature));
+        assert_eq!(verify_token(&tampered).unwrap_err(), "令牌签名无效");
+
+        assert_eq!(verify_token("no-dot").unwrap_err(), "令牌格式无效");
+        assert_eq!(verify_token("a.!!!").unwrap_err(), "令牌格式无效");
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let _library = library_with_password("secret-password");
+
+        let expired = sign_token("admin", 0).unwrap();
+        assert_eq!(verify_token(&expired.token).unwrap_err(), "令牌已过期");
+
+        let media = issue_media_token().unwrap();
+        let claims = verify_token(&media.token).unwrap();
+        assert_eq!(claims.sub, MEDIA_SUBJECT);
+        assert_eq!(claims.exp - claims.iat, MEDIA_TOKEN_TTL_SECS);
+    }
+
+    #[test]
+    fn changing_password_invalidates_issued_tokens() {
+        let _library = library_with_password("secret-password");
+
+        let token = issue_token("admin").unwrap().token;
+        assert!(verify_token(&token).is_ok());
+
+        change_password("secret-password", "another-password").unwrap();
+        assert_eq!(verify_token(&token).unwrap_err(), "令牌签名无效");
+        assert!(verify_token(&issue_token("admin").unwrap().token).is_ok());
+    }
+
+    #[test]
+    fn repeated_failures_lock_out_the_client() {
+        let _library = library_with_password("secret-password");
+        let client = "192.0.2.1";
+
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert_eq!(login("wrong", client).unwrap_err(), "密码错误");
+        }
+
+        // 锁定期间正确的密码也被拒绝，其他客户端不受影响
+        let error = login("secret-password", client).unwrap_err();
+        assert!(error.starts_with("登录失败次数过多"), "{}", error);
+        assert!(login("secret-password", "192.0.2.2").is_ok());
+
+        // 锁定到期后可以再次登录，成功登录清除失败记录
+        FAILED_LOGINS
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .insert(client.to_string(), (0, now_secs() - 1));
+        let response = login("secret-password", client).unwrap();
+        assert!(verify_token(&response.token).is_ok());
+        assert!(!FAILED_LOGINS.lock().unwrap().as_ref().unwrap().contains_key(client));
+    }
+
+    #[test]
+    fn failures_below_the_limit_are_reset_by_a_successful_login() {
+        let _library = library_with_password("secret-password");
+        let client = "192.0.2.3";
+
+        for _ in 0..MAX_FAILED_LOGINS - 1 {
+            assert!(login("wrong", client).is_err());
+        }
+        assert!(login("secret-password", client).is_ok());
+        assert_eq!(login("wrong", client).unwrap_err(), "密码错误");
+        assert!(login("secret-password", client).is_ok());
+    }
+}
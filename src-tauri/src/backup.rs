@@ -0,0 +1,874 @@
+use crate::config::{self, AppConfig};
+use crate::database;
+use crate::upload::{calculate_file_hash, unique_temp_path};
+use chrono::Local;
+use rusqlite::backup::Progress;
+use rusqlite::{params, Connection, DatabaseName, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicBool, Ordering};
+use walkdir::WalkDir;
+
+/// 备份格式版本，恢复时拒绝更新版本的备份
+const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DATABASE_FILE: &str = "library.db";
+const CONFIG_FILE: &str = "config.json";
+const IMAGES_DIR: &str = "images";
+const THUMBNAILS_DIR: &str = "thumbnails";
+
+/// 增量备份目标目录下的共享文件库和各版本目录
+const OBJECTS_DIR: &str = "objects";
+const GENERATIONS_DIR: &str = "generations";
+
+/// 同一时间只允许一个增量备份任务，避免两个任务同时写入共享目录和清理旧版本
+static BACKUP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 备份说明文件
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created_at: String,
+    /// 备份时的图片和缩略图目录，恢复时据此改写数据库中的路径
+    pub images_dir: String,
+    pub thumbnails_dir: String,
+    pub image_count: usize,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// 备份时已不存在的文件，恢复校验时不视为备份不完整
+    #[serde(default)]
+    pub missing: Vec<String>,
+    /// 增量备份中按哈希存放文件的共享目录（相对于本备份目录），完整备份为空
+    #[serde(default)]
+    pub object_store: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupReport {
+    /// 备份目录
+    pub path: String,
+    pub image_count: usize,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// 数据库中有记录但备份时已不存在的文件
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub image_count: usize,
+    /// 复制到图片库的文件数量
+    pub copied_files: usize,
+    /// 图片库中已有相同文件而跳过的数量
+    pub skipped_files: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncrementalBackupReport {
+    /// 新版本目录
+    pub generation: String,
+    pub image_count: usize,
+    /// 新复制到共享目录的文件数量和大小
+    pub copied_files: usize,
+    pub copied_size: u64,
+    /// 共享目录中已有而跳过的文件数量
+    pub skipped_files: usize,
+    pub missing: Vec<String>,
+    /// 超出保留数量而删除的旧版本数量
+    pub pruned_generations: usize,
+    /// 不再被任何版本引用而删除的文件数量
+    pub removed_files: usize,
+}
+
+/// 一个增量备份版本
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupGeneration {
+    pub name: String,
+    pub path: String,
+    pub created_at: String,
+    pub image_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupVerifyReport {
+    /// 校验的版本目录
+    pub generation: String,
+    /// 核对的备份记录数量
+    pub checked: usize,
+    /// 版本数据库中有记录、共享目录中没有的文件
+    pub missing: Vec<String>,
+    /// 共享目录中内容与记录的哈希不一致的文件
+    pub corrupted: Vec<String>,
+    /// 图片库中有、该版本中没有的图片（备份之后新增的）
+    pub not_backed_up: Vec<String>,
+    /// 图片库中的哈希与该版本中的记录不同的图片（备份之后内容有变化的）
+    pub changed: Vec<String>,
+}
+
+/// 数据库快照中的一条图片记录
+struct SnapshotRow {
+    id: i32,
+    path: String,
+    thumbnail_path: Option<String>,
+    size: i64,
+    hash: String,
+}
+
+/// 用 SQLite 在线备份接口把数据库复制到 target，备份期间不阻塞其他读写
+pub fn snapshot_database(target: &Path) -> Result<(), String> {
+    let conn = database::get_connection()
+        .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+    conn.backup(DatabaseName::Main, target, None)
+        .map_err(|e| format!("备份数据库失败: {}", e))
+}
+
+/// 以只读方式打开备份中的数据库
+fn open_snapshot(path: &Path) -> Result<Connection, String> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("打开备份数据库失败: {}", e))
+}
+
+/// 读取数据库中所有图片记录
+fn snapshot_rows(conn: &Connection) -> Result<Vec<SnapshotRow>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, path, thumbnail_path, size, hash FROM images")
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(SnapshotRow {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                thumbnail_path: row.get(2)?,
+                size: row.get(3)?,
+                hash: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("读取图片记录失败: {}", e))
+}
+
+/// 路径相对于目录的部分，不在目录中时返回 None
+fn relative_to(path: &str, base: &Path) -> Option<PathBuf> {
+    Path::new(path).strip_prefix(base).ok().map(Path::to_path_buf)
+}
+
+/// 文件在共享目录中的位置：与图片库一样按文件名（哈希）前两位分目录
+fn object_path(store: &Path, kind: &str, path: &str) -> Option<PathBuf> {
+    let name = Path::new(path).file_name()?;
+    let prefix: String = name.to_string_lossy().chars().take(2).collect();
+    Some(store.join(kind).join(prefix).join(name))
+}
+
+/// 图片文件在备份中的位置
+fn locate_image(dir: &Path, manifest: &BackupManifest, path: &str) -> Option<PathBuf> {
+    match &manifest.object_store {
+        Some(store) => object_path(&dir.join(store), IMAGES_DIR, path),
+        None => relative_to(path, Path::new(&manifest.images_dir)).map(|r| dir.join(IMAGES_DIR).join(r)),
+    }
+}
+
+/// 缩略图文件在备份中的位置
+fn locate_thumbnail(dir: &Path, manifest: &BackupManifest, path: &str) -> Option<PathBuf> {
+    match &manifest.object_store {
+        Some(store) => object_path(&dir.join(store), THUMBNAILS_DIR, path),
+        None => relative_to(path, Path::new(&manifest.thumbnails_dir))
+            .map(|r| dir.join(THUMBNAILS_DIR).join(r)),
+    }
+}
+
+/// 复制文件，自动创建目标目录
+fn copy_file(from: &Path, to: &Path) -> Result<u64, String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    fs::copy(from, to).map_err(|e| format!("复制文件失败 {}: {}", from.display(), e))
+}
+
+/// 写入备份说明文件
+fn write_manifest(dir: &Path, manifest: &BackupManifest) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("序列化备份说明失败: {}", e))?;
+    fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| format!("写入备份说明失败: {}", e))
+}
+
+/// 将配置写入备份目录，不包含密码哈希和令牌签名密钥
+///
+/// 恢复时本来就沿用本机的凭据（见 restore_config），备份中无需保存，也避免备份泄露凭据。
+fn write_config(dir: &Path) -> Result<(), String> {
+    if !config::get_config_path().exists() {
+        return Ok(());
+    }
+
+    let config = AppConfig {
+        password_hash: None,
+        password_change_required: false,
+        token_secret: None,
+        ..config::load_config()
+    };
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("序列化配置失败: {}", e))?;
+    fs::write(dir.join(CONFIG_FILE), json).map_err(|e| format!("写入备份配置失败: {}", e))
+}
+
+/// 将整个图片库备份到 dest_dir 下的新目录：数据库快照、配置文件、图片和缩略图
+///
+/// 先写入 .partial 目录，全部完成后再重命名，中断的备份不会被当作有效备份。
+pub fn backup_library(dest_dir: &Path) -> Result<BackupReport, String> {
+    let name = format!("images-manage-backup-{}", Local::now().format("%Y%m%d-%H%M%S"));
+    let target = dest_dir.join(&name);
+    if target.exists() {
+        return Err(format!("备份目录已存在: {}", target.display()));
+    }
+
+    let partial = dest_dir.join(format!("{}.partial", name));
+    fs::create_dir_all(&partial).map_err(|e| format!("创建备份目录失败: {}", e))?;
+
+    match write_backup(&partial) {
+        Ok(manifest) => {
+            fs::rename(&partial, &target).map_err(|e| format!("完成备份失败: {}", e))?;
+            Ok(BackupReport {
+                path: target.to_string_lossy().to_string(),
+                image_count: manifest.image_count,
+                file_count: manifest.file_count,
+                total_size: manifest.total_size,
+                missing: manifest.missing,
+            })
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&partial);
+            Err(e)
+        }
+    }
+}
+
+fn write_backup(dir: &Path) -> Result<BackupManifest, String> {
+    // 先取数据库快照，再按快照中的记录复制文件，保证两者一致
+    let db_path = dir.join(DATABASE_FILE);
+    snapshot_database(&db_path)?;
+    let rows = snapshot_rows(&open_snapshot(&db_path)?)?;
+    write_config(dir)?;
+
+    let mut manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        created_at: Local::now().to_rfc3339(),
+        images_dir: config::get_images_dir().to_string_lossy().to_string(),
+        thumbnails_dir: config::get_thumbnails_dir().to_string_lossy().to_string(),
+        image_count: rows.len(),
+        file_count: 0,
+        total_size: 0,
+        missing: Vec::new(),
+        object_store: None,
+    };
+
+    for row in &rows {
+        match locate_image(dir, &manifest, &row.path) {
+            Some(target) if Path::new(&row.path).exists() => {
+                manifest.total_size += copy_file(Path::new(&row.path), &target)?;
+                manifest.file_count += 1;
+            }
+            _ => manifest.missing.push(row.path.clone()),
+        }
+
+        // 缩略图缺失不影响恢复（可重新生成），不记录
+        let thumbnail = row.thumbnail_path.as_deref().filter(|p| Path::new(p).exists());
+        if let Some(path) = thumbnail {
+            if let Some(target) = locate_thumbnail(dir, &manifest, path) {
+                manifest.total_size += copy_file(Path::new(path), &target)?;
+                manifest.file_count += 1;
+            }
+        }
+    }
+
+    write_manifest(dir, &manifest)?;
+    Ok(manifest)
+}
+
+/// 读取备份说明文件
+fn read_manifest(dir: &Path) -> Result<BackupManifest, String> {
+    let json = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("读取备份说明失败: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("备份说明格式错误: {}", e))
+}
+
+/// 检查备份是否完整可用：说明文件、数据库完整性，以及所有图片文件存在且大小一致
+pub fn validate_backup(dir: &Path) -> Result<BackupManifest, String> {
+    let manifest = read_manifest(dir)?;
+    if manifest.version > BACKUP_VERSION {
+        return Err(format!("不支持的备份版本: {}", manifest.version));
+    }
+
+    let conn = open_snapshot(&dir.join(DATABASE_FILE))?;
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("检查备份数据库失败: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("备份数据库已损坏: {}", integrity));
+    }
+
+    let mut missing = Vec::new();
+    for row in snapshot_rows(&conn)? {
+        if manifest.missing.contains(&row.path) {
+            continue;
+        }
+        let matches = locate_image(dir, &manifest, &row.path)
+            .and_then(|f| fs::metadata(f).ok())
+            .is_some_and(|meta| meta.len() as i64 == row.size);
+        if !matches {
+            missing.push(row.path);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "备份不完整，{} 个图片文件缺失或大小不符，例如: {}",
+            missing.len(),
+            missing[0]
+        ));
+    }
+
+    Ok(manifest)
+}
+
+/// 把备份中的文件复制到 target，已存在且大小相同的文件跳过（存储按哈希命名）
+fn restore_file(source: &Path, target: &Path, report: &mut RestoreReport) -> Result<(), String> {
+    let unchanged = fs::metadata(target)
+        .ok()
+        .zip(fs::metadata(source).ok())
+        .is_some_and(|(existing, backup)| existing.len() == backup.len());
+    if unchanged {
+        report.skipped_files += 1;
+    } else {
+        copy_file(source, target)?;
+        report.copied_files += 1;
+    }
+    Ok(())
+}
+
+/// 将数据库中以 old_dir 开头的路径改写到 new_dir 下
+fn rebase_paths(conn: &Connection, column: &str, old_dir: &str, new_dir: &Path) -> Result<(), String> {
+    let old_prefix = format!("{}{}", old_dir.trim_end_matches(MAIN_SEPARATOR), MAIN_SEPARATOR);
+    let new_prefix = format!(
+        "{}{}",
+        new_dir.to_string_lossy().trim_end_matches(MAIN_SEPARATOR),
+        MAIN_SEPARATOR
+    );
+    if old_prefix == new_prefix {
+        return Ok(());
+    }
+
+    conn.execute(
+        &format!(
+            "UPDATE images SET {col} = ?2 || substr({col}, length(?1) + 1)
+             WHERE substr({col}, 1, length(?1)) = ?1",
+            col = column
+        ),
+        params![old_prefix, new_prefix],
+    )
+    .map_err(|e| format!("更新文件路径失败: {}", e))?;
+    Ok(())
+}
+
+/// 恢复配置文件，保留本机的存储目录、备份目录和登录凭据
+///
+/// 凭据沿用当前设置，恢复旧备份不会把密码改回旧密码，也不会让已签发的令牌失效。
+fn restore_config(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let json = fs::read_to_string(path).map_err(|e| format!("读取备份配置失败: {}", e))?;
+    let mut restored: AppConfig = serde_json::from_str(&json)
+        .map_err(|e| format!("备份配置格式错误: {}", e))?;
+
+    let current = config::load_config();
+    restored.images_dir = current.images_dir;
+    restored.thumbnails_dir = current.thumbnails_dir;
+    restored.renditions_dir = current.renditions_dir;
+    restored.backup_target_dir = current.backup_target_dir;
+    restored.password_hash = current.password_hash;
+    restored.token_secret = current.token_secret;
+    restored.password_change_required = current.password_change_required;
+    config::save_config(&restored)
+}
+
+/// 校验并从备份目录（完整备份或增量备份的某个版本）恢复图片库
+///
+/// 图片和缩略图复制到当前配置的目录（不删除图片库中已有的文件），
+/// 数据库通过在线备份接口整体替换，路径改写到当前目录下。
+pub fn restore_library(backup_dir: &Path) -> Result<RestoreReport, String> {
+    let manifest = validate_backup(backup_dir)?;
+
+    let images_dir = config::get_images_dir();
+    let thumbnails_dir = config::get_thumbnails_dir();
+    let old_images_dir = PathBuf::from(&manifest.images_dir);
+    let old_thumbnails_dir = PathBuf::from(&manifest.thumbnails_dir);
+
+    let mut report = RestoreReport {
+        image_count: manifest.image_count,
+        copied_files: 0,
+        skipped_files: 0,
+    };
+    let rows = snapshot_rows(&open_snapshot(&backup_dir.join(DATABASE_FILE))?)?;
+    for row in &rows {
+        let image = locate_image(backup_dir, &manifest, &row.path)
+            .zip(relative_to(&row.path, &old_images_dir));
+        if let Some((source, relative)) = image.filter(|(source, _)| source.exists()) {
+            restore_file(&source, &images_dir.join(relative), &mut report)?;
+        }
+
+        let thumbnail = row.thumbnail_path.as_deref().and_then(|path| {
+            locate_thumbnail(backup_dir, &manifest, path).zip(relative_to(path, &old_thumbnails_dir))
+        });
+        if let Some((source, relative)) = thumbnail.filter(|(source, _)| source.exists()) {
+            restore_file(&source, &thumbnails_dir.join(relative), &mut report)?;
+        }
+    }
+
+    let mut conn = database::get_connection()
+        .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+    conn.restore(DatabaseName::Main, backup_dir.join(DATABASE_FILE), None::<fn(Progress)>)
+        .map_err(|e| format!("恢复数据库失败: {}", e))?;
+    rebase_paths(&conn, "path", &manifest.images_dir, &images_dir)?;
+    rebase_paths(&conn, "thumbnail_path", &manifest.thumbnails_dir, &thumbnails_dir)?;
+    drop(conn);
+
+    // 旧版本的备份可能缺少新增的列和索引
+    database::init_database().map_err(|e| format!("升级数据库失败: {}", e))?;
+
+    restore_config(&backup_dir.join(CONFIG_FILE))?;
+
+    Ok(report)
+}
+
+/// 配置的增量备份目标目录
+fn incremental_target() -> Result<PathBuf, String> {
+    config::load_config()
+        .backup_target_dir
+        .map(PathBuf::from)
+        .ok_or_else(|| "未配置增量备份目录".to_string())
+}
+
+/// 复制到共享目录：已存在且大小与源文件相同的文件跳过（文件按哈希命名），大小不符的重新复制；
+/// 先写临时文件再重命名，中断时不留下不完整的文件
+///
+/// 备份时不重新计算已有文件的哈希，内容校验由 verify_incremental_backup 负责。
+fn store_object(source: &Path, object: &Path) -> Result<Option<u64>, String> {
+    let same_size = fs::metadata(object)
+        .ok()
+        .zip(fs::metadata(source).ok())
+        .is_some_and(|(stored, source)| stored.len() == source.len());
+    if same_size {
+        return Ok(None);
+    }
+
+    let tmp = unique_temp_path(object);
+    let size = copy_file(source, &tmp)?;
+    fs::rename(&tmp, object).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("写入备份文件失败: {}", e)
+    })?;
+    Ok(Some(size))
+}
+
+/// 增量备份到配置的目标目录
+///
+/// 图片和缩略图按哈希存入共享目录，只复制新增的文件；每次生成一个包含数据库快照的新版本，
+/// 超出 backup_generations 的旧版本和不再被引用的文件随后删除。
+pub fn incremental_backup() -> Result<IncrementalBackupReport, String> {
+    if BACKUP_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("已有增量备份正在进行".to_string());
+    }
+
+    let result = run_incremental_backup();
+    BACKUP_RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+fn run_incremental_backup() -> Result<IncrementalBackupReport, String> {
+    let target = incremental_target()?;
+    let store = target.join(OBJECTS_DIR);
+    let generations_dir = target.join(GENERATIONS_DIR);
+
+    let name = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let generation = generations_dir.join(&name);
+    if generation.exists() {
+        return Err(format!("备份版本已存在: {}", generation.display()));
+    }
+    let partial = generations_dir.join(format!("{}.partial", name));
+    fs::create_dir_all(&partial).map_err(|e| format!("创建备份目录失败: {}", e))?;
+
+    let mut report = match write_generation(&partial, &store) {
+        Ok(report) => report,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&partial);
+            return Err(e);
+        }
+    };
+    fs::rename(&partial, &generation).map_err(|e| format!("完成备份失败: {}", e))?;
+    report.generation = generation.to_string_lossy().to_string();
+
+    let keep = config::load_config().backup_generations.max(1) as usize;
+    report.pruned_generations = prune_generations(&generations_dir, keep)?;
+    report.removed_files = collect_garbage(&target)?;
+
+    Ok(report)
+}
+
+fn write_generation(dir: &Path, store: &Path) -> Result<IncrementalBackupReport, String> {
+    let db_path = dir.join(DATABASE_FILE);
+    snapshot_database(&db_path)?;
+    let rows = snapshot_rows(&open_snapshot(&db_path)?)?;
+    write_config(dir)?;
+
+    let mut report = IncrementalBackupReport {
+        generation: String::new(),
+        image_count: rows.len(),
+        copied_files: 0,
+        copied_size: 0,
+        skipped_files: 0,
+        missing: Vec::new(),
+        pruned_generations: 0,
+        removed_files: 0,
+    };
+
+    let mut files = Vec::new();
+    for row in &rows {
+        match object_path(store, IMAGES_DIR, &row.path) {
+            Some(object) if Path::new(&row.path).exists() => {
+                files.push((PathBuf::from(&row.path), object));
+            }
+            _ => report.missing.push(row.path.clone()),
+        }
+
+        if let Some(path) = row.thumbnail_path.as_deref().filter(|p| Path::new(p).exists()) {
+            if let Some(object) = object_path(store, THUMBNAILS_DIR, path) {
+                files.push((PathBuf::from(path), object));
+            }
+        }
+    }
+
+    let mut total_size = 0;
+    for (source, object) in &files {
+        match store_object(source, object)? {
+            Some(size) => {
+                report.copied_files += 1;
+                report.copied_size += size;
+            }
+            None => report.skipped_files += 1,
+        }
+        total_size += fs::metadata(object).map(|meta| meta.len()).unwrap_or(0);
+    }
+
+    let manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        created_at: Local::now().to_rfc3339(),
+        images_dir: config::get_images_dir().to_string_lossy().to_string(),
+        thumbnails_dir: config::get_thumbnails_dir().to_string_lossy().to_string(),
+        image_count: rows.len(),
+        file_count: files.len(),
+        total_size,
+        missing: report.missing.clone(),
+        object_store: Some(Path::new("..").join("..").join(OBJECTS_DIR).to_string_lossy().to_string()),
+    };
+    write_manifest(dir, &manifest)?;
+
+    Ok(report)
+}
+
+/// 列出已完成的增量备份版本，最新的在前
+pub fn list_backup_generations() -> Result<Vec<BackupGeneration>, String> {
+    let generations_dir = incremental_target()?.join(GENERATIONS_DIR);
+    if !generations_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&generations_dir).map_err(|e| format!("读取备份目录失败: {}", e))?;
+    let mut generations: Vec<BackupGeneration> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let manifest = read_manifest(&entry.path()).ok()?;
+            Some(BackupGeneration {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: entry.path().to_string_lossy().to_string(),
+                created_at: manifest.created_at,
+                image_count: manifest.image_count,
+            })
+        })
+        .collect();
+    generations.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(generations)
+}
+
+/// 只保留最新的 keep 个版本，同时清理中断留下的 .partial 目录，返回删除的版本数量
+fn prune_generations(generations_dir: &Path, keep: usize) -> Result<usize, String> {
+    let entries = fs::read_dir(generations_dir).map_err(|e| format!("读取备份目录失败: {}", e))?;
+    let mut names = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".partial") {
+            let _ = fs::remove_dir_all(entry.path());
+        } else if entry.path().is_dir() {
+            names.push(name);
+        }
+    }
+
+    names.sort_by(|a, b| b.cmp(a));
+    let mut pruned = 0;
+    for name in names.iter().skip(keep) {
+        fs::remove_dir_all(generations_dir.join(name))
+            .map_err(|e| format!("删除旧备份失败: {}", e))?;
+        pruned += 1;
+    }
+    Ok(pruned)
+}
+
+/// 删除共享目录中不再被任何版本引用的文件，返回删除的数量
+///
+/// 任一版本无法读取时不删除任何文件，避免误删仍需要的备份。
+fn collect_garbage(target: &Path) -> Result<usize, String> {
+    let store = target.join(OBJECTS_DIR);
+    let generations_dir = target.join(GENERATIONS_DIR);
+
+    let mut referenced = HashSet::new();
+    let entries = fs::read_dir(&generations_dir).map_err(|e| format!("读取备份目录失败: {}", e))?;
+    for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+        let rows = snapshot_rows(&open_snapshot(&entry.path().join(DATABASE_FILE))?)?;
+        for row in rows {
+            referenced.extend(object_path(&store, IMAGES_DIR, &row.path));
+            if let Some(thumbnail_path) = &row.thumbnail_path {
+                referenced.extend(object_path(&store, THUMBNAILS_DIR, thumbnail_path));
+            }
+        }
+    }
+
+    let removed = WalkDir::new(&store)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && !referenced.contains(entry.path()))
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count();
+    Ok(removed)
+}
+
+/// 逐个核对最新的增量备份：该版本数据库中的每张图片在共享目录中都有文件，且哈希与记录一致；
+/// 并与当前图片库对比，列出还没有备份或备份之后有变化的图片
+pub fn verify_incremental_backup() -> Result<BackupVerifyReport, String> {
+    let latest = list_backup_generations()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "还没有增量备份".to_string())?;
+    let dir = Path::new(&latest.path);
+    let manifest = read_manifest(dir)?;
+
+    // 按该版本自己的数据库快照核对共享目录中的文件
+    let rows: Vec<SnapshotRow> = snapshot_rows(&open_snapshot(&dir.join(DATABASE_FILE))?)?
+        .into_iter()
+        .filter(|row| !manifest.missing.contains(&row.path))
+        .collect();
+
+    let mut report = BackupVerifyReport {
+        generation: latest.path.clone(),
+        checked: rows.len(),
+        missing: Vec::new(),
+        corrupted: Vec::new(),
+        not_backed_up: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    // 当前图片库中的图片按哈希判断是否已备份；文件已丢失的无法备份，留给库校验处理
+    let backed_up: HashSet<&str> = rows.iter().map(|row| row.hash.as_str()).collect();
+    let backup_ids: HashSet<i32> = rows.iter().map(|row| row.id).collect();
+    let conn = database::get_connection().map_err(|e| format!("获取数据库连接失败: {}", e))?;
+    for row in snapshot_rows(&conn)? {
+        if backed_up.contains(row.hash.as_str()) || !Path::new(&row.path).exists() {
+            continue;
+        }
+        if backup_ids.contains(&row.id) {
+            report.changed.push(row.path);
+        } else {
+            report.not_backed_up.push(row.path);
+        }
+    }
+
+    for row in &rows {
+        match locate_image(dir, &manifest, &row.path).filter(|object| object.exists()) {
+            None => report.missing.push(row.path.clone()),
+            Some(object) => {
+                if calculate_file_hash(&object).ok().as_deref() != Some(row.hash.as_str()) {
+                    report.corrupted.push(row.path.clone());
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth;
+    use crate::test_support::TestLibrary;
+    use crate::trash;
+
+    #[test]
+    fn full_backup_restores_images_and_settings_but_not_credentials() {
+        let library = TestLibrary::new();
+        config::save_config(&AppConfig {
+            password_hash: Some(auth::hash_password("secret-password").unwrap()),
+            token_secret: Some("backup-test-secret".to_string()),
+            ..config::load_config()
+        })
+        .unwrap();
+        let kept = library.import_png("a.png", [200, 30, 30]);
+        let purged = library.import_png("b.png", [30, 200, 30]);
+
+        let report = backup_library(&library.root().join("backups")).unwrap();
+        assert_eq!(report.image_count, 2);
+        assert!(report.missing.is_empty());
+        let backup_dir = PathBuf::from(&report.path);
+        assert!(validate_backup(&backup_dir).is_ok());
+
+        let saved: AppConfig =
+            serde_json::from_str(&fs::read_to_string(backup_dir.join(CONFIG_FILE)).unwrap()).unwrap();
+        assert!(saved.password_hash.is_none());
+        assert!(saved.token_secret.is_none());
+
+        // 备份之后永久删除一张图片，并修改设置和密码
+        trash::trash_image(purged).unwrap();
+        trash::empty_trash().unwrap();
+        config::save_config(&AppConfig {
+            trash_retention_days: 5,
+            password_hash: Some(auth::hash_password("newer-password").unwrap()),
+            ..config::load_config()
+        })
+        .unwrap();
+
+        let restored = restore_library(&backup_dir).unwrap();
+        assert_eq!(restored.image_count, 2);
+        assert_eq!(restored.copied_files + restored.skipped_files, report.file_count);
+        for id in [kept, purged] {
+            let record = database::get_image_by_id(id).unwrap();
+            assert!(record.deleted_at.is_none());
+            assert_eq!(calculate_file_hash(Path::new(&record.path)).unwrap(), record.hash);
+        }
+
+        let config = config::load_config();
+        assert_eq!(config.trash_retention_days, 30);
+        assert_eq!(config.token_secret.as_deref(), Some("backup-test-secret"));
+        assert!(auth::verify_password("newer-password").unwrap());
+    }
+
+    /// 配置增量备份目录和保留的版本数量，返回备份目录
+    fn use_backup_target(library: &TestLibrary, generations: u32) -> PathBuf {
+        let target = library.root().join("nas");
+        config::save_config(&AppConfig {
+            backup_target_dir: Some(target.to_string_lossy().to_string()),
+            backup_generations: generations,
+            ..config::load_config()
+        })
+        .unwrap();
+        target
+    }
+
+    /// 备份版本以秒为单位命名，把刚完成的版本改成更早的名字，以便同一秒内再次备份
+    fn backdate(report: &IncrementalBackupReport, name: &str) {
+        let generation = Path::new(&report.generation);
+        fs::rename(generation, generation.with_file_name(name)).unwrap();
+    }
+
+    fn image_object(target: &Path, id: i32) -> PathBuf {
+        let path = database::get_image_by_id(id).unwrap().path;
+        object_path(&target.join(OBJECTS_DIR), IMAGES_DIR, &path).unwrap()
+    }
+
+    #[test]
+    fn incremental_backup_copies_new_files_and_verify_compares_with_the_library() {
+        let library = TestLibrary::new();
+        let target = use_backup_target(&library, 7);
+        let first = library.import_png("a.png", [200, 30, 30]);
+        library.import_png("b.png", [30, 200, 30]);
+
+        let report = incremental_backup().unwrap();
+        assert_eq!(report.image_count, 2);
+        assert!(report.copied_files >= 2);
+        assert_eq!(report.skipped_files, 0);
+
+        let verify = verify_incremental_backup().unwrap();
+        assert_eq!(verify.checked, 2);
+        assert!(verify.missing.is_empty() && verify.corrupted.is_empty());
+        assert!(verify.not_backed_up.is_empty() && verify.changed.is_empty());
+
+        // 备份之后新增的图片
+        let third = library.import_png("c.png", [30, 30, 200]);
+        let verify = verify_incremental_backup().unwrap();
+        assert_eq!(verify.not_backed_up, [database::get_image_by_id(third).unwrap().path]);
+
+        // 共享目录中的文件内容被改坏但大小不变：备份时按大小跳过，由校验发现
+        let object = image_object(&target, first);
+        let size = fs::metadata(&object).unwrap().len() as usize;
+        fs::write(&object, vec![0u8; size]).unwrap();
+        let verify = verify_incremental_backup().unwrap();
+        assert_eq!(verify.corrupted, [database::get_image_by_id(first).unwrap().path]);
+
+        backdate(&report, "20000101-000000");
+        let previous = report.copied_files;
+        let report = incremental_backup().unwrap();
+        assert_eq!(report.image_count, 3);
+        assert_eq!(report.skipped_files, previous);
+        assert!(report.copied_files >= 1);
+        assert!(verify_incremental_backup().unwrap().not_backed_up.is_empty());
+    }
+
+    #[test]
+    fn old_generations_are_pruned_and_unreferenced_files_removed() {
+        let library = TestLibrary::new();
+        let target = use_backup_target(&library, 2);
+
+        let dropped = library.import_png("a.png", [200, 30, 30]);
+        let dropped_object = image_object(&target, dropped);
+        backdate(&incremental_backup().unwrap(), "20000101-000000");
+
+        trash::trash_image(dropped).unwrap();
+        trash::empty_trash().unwrap();
+        let kept = library.import_png("b.png", [30, 200, 30]);
+        let kept_object = image_object(&target, kept);
+        backdate(&incremental_backup().unwrap(), "20000102-000000");
+
+        // 中断的备份留下的目录也一并清理
+        let partial = target.join(GENERATIONS_DIR).join("20000103-000000.partial");
+        fs::create_dir_all(&partial).unwrap();
+
+        library.import_png("c.png", [30, 30, 200]);
+        let report = incremental_backup().unwrap();
+        assert_eq!(report.pruned_generations, 1);
+        assert!(report.removed_files >= 1);
+
+        let names: Vec<String> = list_backup_generations()
+            .unwrap()
+            .into_iter()
+            .map(|generation| generation.name)
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[1], "20000102-000000");
+        assert!(!partial.exists());
+        assert!(!dropped_object.exists());
+        assert!(kept_object.exists());
+    }
+
+    #[test]
+    fn only_one_incremental_backup_runs_at_a_time() {
+        let library = TestLibrary::new();
+        use_backup_target(&library, 7);
+
+        BACKUP_RUNNING.store(true, Ordering::SeqCst);
+        let result = incremental_backup();
+        BACKUP_RUNNING.store(false, Ordering::SeqCst);
+        assert!(result.is_err());
+        assert!(incremental_backup().is_ok());
+    }
+}
@@ -1,12 +1,34 @@
+use crate::auth::{self, LoginResponse};
+use crate::backup::{
+    self, BackupGeneration, BackupReport, BackupVerifyReport, IncrementalBackupReport, RestoreReport,
+};
 use crate::config::{self, AppConfig};
-use crate::database::{self, ImageRecord};
-use crate::image::validate_image_format;
+use crate::database::{self, ImageMetadata, ImageRecord, ListOptions, TagCount, VerificationRecord};
+use crate::image::{decode_image, encode_image, validate_image_format, OutputFormat, SourceFormat};
 use crate::api_server;
+use crate::importer::{self, ImportReport};
+use crate::integrity::{self, IntegrityReport, RepairAction, RepairReport};
+use crate::metadata::{self, DimensionScanReport, MetadataScanReport};
+use crate::pairing::{self, PairingCode};
+use crate::rendition::{self, RenditionParams};
+use crate::scrub::{self, ScrubReport};
+use crate::search::{self, DimensionFilter, SearchQuery};
+use crate::trash;
+use crate::thumbnail::{self, ThumbnailReport};
 use crate::upload;
+use crate::watcher;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
-use tauri::command;
+use tauri::{command, AppHandle, Emitter};
+
+/// 分页查询结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImagePageInfo {
+    pub images: Vec<ImageInfo>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SystemInfo {
@@ -23,6 +45,21 @@ pub struct ImageInfo {
     pub thumbnail_path: Option<String>,
     pub description: Option<String>,
     pub created_at: String,
+    pub mime_type: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub aspect_ratio: Option<f64>,
+    /// RAW+JPEG 配对中另一张图片的 ID
+    pub paired_id: Option<i32>,
+    /// 移入回收站的时间
+    pub deleted_at: Option<String>,
+    /// 最近一次哈希校验的时间和结果
+    pub verified_at: Option<String>,
+    pub verify_status: Option<String>,
+    pub metadata: Option<ImageMetadata>,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 impl From<ImageRecord> for ImageInfo {
@@ -35,6 +72,17 @@ impl From<ImageRecord> for ImageInfo {
             thumbnail_path: record.thumbnail_path,
             description: record.description,
             created_at: record.created_at,
+            mime_type: record.mime_type,
+            width: record.width,
+            height: record.height,
+            aspect_ratio: record.aspect_ratio,
+            paired_id: record.paired_id,
+            deleted_at: record.deleted_at,
+            verified_at: record.verified_at,
+            verify_status: record.verify_status,
+            metadata: record.metadata,
+            tags: record.tags,
+            snippet: record.snippet,
         }
     }
 }
@@ -47,6 +95,17 @@ pub struct UploadResult {
     pub file_size: Option<i64>,
 }
 
+impl From<upload::UploadResult> for UploadResult {
+    fn from(result: upload::UploadResult) -> Self {
+        Self {
+            success: result.success,
+            message: result.message,
+            image_id: result.image_id,
+            file_size: result.file_size,
+        }
+    }
+}
+
 #[command]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -70,26 +129,38 @@ pub fn validate_image(path: String) -> Result<bool, String> {
     validate_image_format(&path)
 }
 
+/// 登录，成功时返回签名令牌
 #[command]
-pub async fn login(password: String) -> Result<String, String> {
-    if password != "admin" {
-        return Err("密码错误".to_string());
-    }
+pub async fn login(password: String) -> Result<LoginResponse, String> {
+    tauri::async_runtime::spawn_blocking(move || auth::login(&password, "local"))
+        .await
+        .map_err(|e| format!("登录任务失败: {}", e))?
+}
 
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let expiration = SystemTime::now() + std::time::Duration::from_secs(86400);
+/// 修改登录密码，之前签发的令牌全部失效
+#[command]
+pub async fn change_password(current_password: String, new_password: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || auth::change_password(&current_password, &new_password))
+        .await
+        .map_err(|e| format!("修改密码任务失败: {}", e))?
+}
 
-    let payload = serde_json::json!({
-        "exp": expiration.duration_since(UNIX_EPOCH).unwrap().as_secs(),
-        "sub": "admin",
-    });
+/// 获取本机网络地址，供配对页面显示手机端访问地址
+#[command]
+pub fn get_network_info() -> api_server::NetworkInfo {
+    api_server::network_info()
+}
 
-    Ok(serde_json::to_string(&payload).map_err(|e| e.to_string())?)
+/// 创建手机配对码，format 为 png（默认）或 svg
+#[command]
+pub fn create_pairing_code(format: Option<String>) -> Result<PairingCode, String> {
+    pairing::create_pairing_code(format.as_deref().unwrap_or("png"))
 }
 
+/// 列出图库中的图片，不含回收站中的图片和已与 JPEG 配对的 RAW
 #[command]
 pub fn get_all_images() -> Result<Vec<ImageInfo>, String> {
-    match database::get_all_images() {
+    match database::search_images(&SearchQuery::default()) {
         Ok(records) => {
             let images: Vec<ImageInfo> = records.into_iter().map(Into::into).collect();
             Ok(images)
@@ -98,19 +169,12 @@ pub fn get_all_images() -> Result<Vec<ImageInfo>, String> {
     }
 }
 
+/// 搜索图片，查询语法见 search::SearchQuery
 #[command]
 pub fn search_images(query: String) -> Result<Vec<ImageInfo>, String> {
-    if query.trim().is_empty() {
-        return match database::get_all_images() {
-            Ok(records) => {
-                let images: Vec<ImageInfo> = records.into_iter().map(Into::into).collect();
-                Ok(images)
-            }
-            Err(e) => Err(format!("搜索图片失败: {}", e)),
-        };
-    }
+    let search_query = search::parse_query(&query)?;
 
-    match database::search_images(&query) {
+    match database::search_images(&search_query) {
         Ok(records) => {
             let images: Vec<ImageInfo> = records.into_iter().map(Into::into).collect();
             Ok(images)
@@ -119,26 +183,160 @@ pub fn search_images(query: String) -> Result<Vec<ImageInfo>, String> {
     }
 }
 
+/// 分页、排序查询图片，query 为空时列出全部图片，filter 为尺寸筛选条件
+#[command]
+pub fn list_images(
+    query: Option<String>,
+    options: Option<ListOptions>,
+    filter: Option<DimensionFilter>,
+) -> Result<ImagePageInfo, String> {
+    let mut search_query = search::parse_query(query.as_deref().unwrap_or(""))?;
+    if let Some(filter) = filter {
+        filter.apply(&mut search_query);
+    }
+    let options = options.unwrap_or_default();
+    database::validate_list_options(&search_query, &options)?;
+
+    match database::query_images(&search_query, &options) {
+        Ok(page) => Ok(ImagePageInfo {
+            images: page.images.into_iter().map(Into::into).collect(),
+            total: page.total,
+            next_cursor: page.next_cursor,
+        }),
+        Err(e) => Err(format!("查询图片失败: {}", e)),
+    }
+}
+
 #[command]
 pub fn upload_image(path: String) -> Result<UploadResult, String> {
-    upload::upload_image_from_path(&path).map(|result| UploadResult {
-        success: result.success,
-        message: result.message,
-        image_id: result.image_id,
-        file_size: result.file_size,
+    upload::upload_image_from_path(&path).map(Into::into)
+}
+
+/// 批量导入文件夹中的图片（默认包含子目录），导入过程中发送 import-progress 事件
+#[command]
+pub async fn import_directory(app: AppHandle, path: String, recursive: Option<bool>) -> Result<ImportReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        importer::import_directory(Path::new(&path), recursive.unwrap_or(true), |progress| {
+            let _ = app.emit(importer::PROGRESS_EVENT, progress);
+        })
     })
+    .await
+    .map_err(|e| format!("导入任务失败: {}", e))?
+}
+
+/// 取消正在进行的批量导入
+#[command]
+pub fn cancel_import() -> bool {
+    importer::cancel_import()
+}
+
+/// 为缺少缩略图的图片补生成缩略图
+#[command]
+pub async fn generate_missing_thumbnails() -> Result<ThumbnailReport, String> {
+    tauri::async_runtime::spawn_blocking(thumbnail::generate_missing_thumbnails)
+        .await
+        .map_err(|e| format!("生成缩略图任务失败: {}", e))?
+}
+
+/// 重新扫描所有图片的 EXIF 信息
+#[command]
+pub async fn rescan_metadata() -> Result<MetadataScanReport, String> {
+    tauri::async_runtime::spawn_blocking(metadata::rescan_all_metadata)
+        .await
+        .map_err(|e| format!("扫描 EXIF 任务失败: {}", e))?
+}
+
+/// 为旧版本导入、缺少尺寸信息的图片补充宽高
+#[command]
+pub async fn backfill_dimensions() -> Result<DimensionScanReport, String> {
+    tauri::async_runtime::spawn_blocking(metadata::backfill_dimensions)
+        .await
+        .map_err(|e| format!("补充图片尺寸任务失败: {}", e))?
 }
 
 /// 获取应用配置
 #[command]
 pub fn get_config() -> AppConfig {
-    config::load_config()
+    // 密码哈希和令牌密钥不返回给前端
+    AppConfig {
+        password_hash: None,
+        token_secret: None,
+        ..config::load_config()
+    }
 }
 
 /// 更新应用配置
 #[command]
 pub fn update_config(config: AppConfig) -> Result<(), String> {
-    config::save_config(&config)
+    let current = config::load_config();
+    let pinyin_changed = current.search_pinyin != config.search_pinyin;
+    let watched_folders_changed = current.watched_folders != config.watched_folders;
+
+    // 密码和令牌密钥只能通过 change_password 修改
+    let config = AppConfig {
+        password_hash: current.password_hash,
+        password_change_required: current.password_change_required,
+        token_secret: current.token_secret,
+        ..config
+    };
+    config::save_config(&config)?;
+
+    // 拼音设置变化后需要重建全文索引
+    if pinyin_changed {
+        search::set_pinyin_enabled(config.search_pinyin);
+        database::rebuild_search_index()
+            .map_err(|e| format!("重建搜索索引失败: {}", e))?;
+    }
+
+    if watched_folders_changed {
+        watcher::restart()?;
+    }
+
+    Ok(())
+}
+
+/// 添加监视文件夹，返回更新后的监视列表
+#[command]
+pub fn add_watched_folder(path: String) -> Result<Vec<String>, String> {
+    let folder = fs::canonicalize(&path)
+        .map_err(|e| format!("无法访问文件夹: {}", e))?;
+    if !folder.is_dir() {
+        return Err(format!("不是文件夹: {}", path));
+    }
+
+    let folder = folder.to_string_lossy().to_string();
+    let mut config = config::load_config();
+    if !config.watched_folders.contains(&folder) {
+        config.watched_folders.push(folder);
+        config::save_config(&config)?;
+        watcher::restart()?;
+    }
+
+    Ok(config.watched_folders)
+}
+
+/// 移除监视文件夹，返回更新后的监视列表
+#[command]
+pub fn remove_watched_folder(path: String) -> Result<Vec<String>, String> {
+    let mut config = config::load_config();
+    let count = config.watched_folders.len();
+    config.watched_folders.retain(|folder| folder != &path);
+
+    if config.watched_folders.len() != count {
+        config::save_config(&config)?;
+        watcher::restart()?;
+    }
+
+    Ok(config.watched_folders)
+}
+
+/// 重建全文搜索索引
+#[command]
+pub async fn rebuild_search_index() -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(database::rebuild_search_index)
+        .await
+        .map_err(|e| format!("重建搜索索引任务失败: {}", e))?
+        .map_err(|e| format!("重建搜索索引失败: {}", e))
 }
 
 /// 获取图片存储目录
@@ -181,30 +379,65 @@ pub fn get_image_data(path: String) -> Result<String, String> {
     }
 
     // 读取文件内容
-    let data = fs::read(file_path)
+    let mut data = fs::read(file_path)
         .map_err(|e| format!("读取文件失败: {}", e))?;
 
+    // WebView 无法显示的格式（如 HEIC）转换为 JPEG
+    let mut format = SourceFormat::from_magic(&data);
+    if format.is_some_and(|f| f.needs_browser_conversion()) {
+        let img = decode_image(&data)?;
+        data = encode_image(&img, OutputFormat::Jpeg, 90)?;
+        format = Some(SourceFormat::Jpeg);
+    }
+
     // 转换为 base64
     let base64_string = general_purpose::STANDARD.encode(&data);
 
-    // 根据文件扩展名确定 MIME 类型
-    let mime_type = match file_path.extension().and_then(|e| e.to_str()) {
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("png") => "image/png",
-        Some("webp") => "image/webp",
-        Some("gif") => "image/gif",
-        _ => "image/jpeg",
+    // 优先按文件内容确定 MIME 类型，无法识别时按扩展名
+    let mime_type = match format {
+        Some(format) => format.mime_type(),
+        None => match file_path.extension().and_then(|e| e.to_str()) {
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("png") => "image/png",
+            Some("webp") => "image/webp",
+            Some("gif") => "image/gif",
+            _ => "image/jpeg",
+        },
     };
 
     Ok(format!("data:{};base64,{}", mime_type, base64_string))
 }
 
-/// 更新图片信息
+/// 获取缩放后的图片，返回 base64 编码的 data URL
+#[command]
+pub async fn get_image_rendition(
+    id: i32,
+    width: Option<u32>,
+    height: Option<u32>,
+    fit: Option<String>,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<String, String> {
+    let params = RenditionParams { width, height, fit, format, quality };
+    let spec = params.parse()?;
+
+    let record = database::get_image_by_id(id)
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+
+    let rendition_path = tauri::async_runtime::spawn_blocking(move || {
+        rendition::get_or_create_rendition(&record, &spec)
+    })
+    .await
+    .map_err(|e| format!("生成缩放图任务失败: {}", e))??;
+
+    get_image_data(rendition_path.to_string_lossy().to_string())
+}
+
+/// 更新图片信息（存储路径始终按内容哈希命名，重命名不改动磁盘文件）
 #[command]
 pub fn update_image_info(id: i32, filename: Option<String>, description: Option<String>) -> Result<(), String> {
     use rusqlite::params;
     use crate::database::get_connection;
-    use std::path::Path;
 
     let conn = get_connection()
         .map_err(|e| format!("获取数据库连接失败: {}", e))?;
@@ -222,7 +455,7 @@ pub fn update_image_info(id: i32, filename: Option<String>, description: Option<
         }
     };
 
-    // 处理文件名更新
+    // 处理文件名更新（只改显示名，不移动存储文件）
     if let Some(new_filename) = filename {
         // 验证文件名不为空
         if new_filename.trim().is_empty() {
@@ -245,51 +478,13 @@ pub fn update_image_info(id: i32, filename: Option<String>, description: Option<
             format!("{}.{}", new_filename.trim(), extension)
         };
 
-        // 检查文件名是否已存在（排除当前图片）
-        let mut existing = conn.prepare(
-            "SELECT id FROM images WHERE filename = ?1 AND id != ?2"
+        conn.execute(
+            "UPDATE images SET filename = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![&new_filename_with_ext, id],
         ).map_err(|e| {
             conn.execute("ROLLBACK", []).ok();
-            format!("准备查询失败: {}", e)
+            format!("更新文件名失败: {}", e)
         })?;
-
-        let exists = existing.exists(params![&new_filename_with_ext, id])
-            .map_err(|e| {
-                conn.execute("ROLLBACK", []).ok();
-                format!("检查文件名是否存在失败: {}", e)
-            })?;
-
-        if exists {
-            conn.execute("ROLLBACK", []).ok();
-            return Err(format!("文件名 '{}' 已存在，请使用其他名称", new_filename_with_ext));
-        }
-
-        // 重命名文件
-        if old_path.exists() {
-            let new_path = old_path.with_file_name(&new_filename_with_ext);
-
-            // 转换新路径为字符串，处理非UTF-8字符
-            let new_path_str = new_path.to_str()
-                .ok_or_else(|| {
-                    conn.execute("ROLLBACK", []).ok();
-                    format!("新路径包含无效的UTF-8字符: {:?}", new_path)
-                })?;
-
-            fs::rename(&old_path, &new_path)
-                .map_err(|e| {
-                    conn.execute("ROLLBACK", []).ok();
-                    format!("重命名文件失败: {}", e)
-                })?;
-
-            // 更新数据库
-            conn.execute(
-                "UPDATE images SET filename = ?1, path = ?2, updated_at = datetime('now') WHERE id = ?3",
-                params![&new_filename_with_ext, new_path_str, id],
-            ).map_err(|e| {
-                conn.execute("ROLLBACK", []).ok();
-                format!("更新文件名失败: {}", e)
-            })?;
-        }
     }
 
     // 处理描述更新
@@ -316,60 +511,185 @@ pub fn update_image_info(id: i32, filename: Option<String>, description: Option<
 /// 根据ID获取图片信息
 #[command]
 pub fn get_image_by_id(id: i32) -> Result<ImageInfo, String> {
-    use rusqlite::params;
-    use crate::database::get_connection;
+    database::get_image_by_id(id)
+        .map(Into::into)
+        .map_err(|e| format!("查询图片失败: {}", e))
+}
 
-    let conn = get_connection()
-        .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+/// 删除图片（移入回收站，可恢复）
+#[command]
+pub fn delete_image(id: i32) -> Result<(), String> {
+    trash::trash_image(id)
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT id, filename, path, size, thumbnail_path, description, created_at FROM images WHERE id = ?1"
-    )
-    .map_err(|e| format!("准备查询失败: {}", e))?;
-
-    let image = stmt.query_row(params![id], |row| {
-        Ok(ImageInfo {
-            id: row.get(0)?,
-            filename: row.get(1)?,
-            path: row.get(2)?,
-            size: row.get(3)?,
-            thumbnail_path: row.get(4)?,
-            description: row.get(5)?,
-            created_at: row.get(6)?,
+/// 列出回收站中的图片
+#[command]
+pub fn list_trash() -> Result<Vec<ImageInfo>, String> {
+    trash::list_trash().map(|records| records.into_iter().map(Into::into).collect())
+}
+
+/// 从回收站恢复图片，返回恢复的数量和失败的图片
+#[command]
+pub async fn restore_images(ids: Vec<i32>) -> Result<trash::RestoreReport, String> {
+    tauri::async_runtime::spawn_blocking(move || trash::restore_images(&ids))
+        .await
+        .map_err(|e| format!("恢复图片任务失败: {}", e))
+}
+
+/// 清空回收站，返回永久删除的图片数量
+#[command]
+pub async fn empty_trash() -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(trash::empty_trash)
+        .await
+        .map_err(|e| format!("清空回收站任务失败: {}", e))?
+}
+
+/// 检查图片库完整性，check_hashes 为 true（默认）时重新计算文件哈希
+#[command]
+pub async fn verify_library(check_hashes: Option<bool>) -> Result<IntegrityReport, String> {
+    let check_hashes = check_hashes.unwrap_or(true);
+    tauri::async_runtime::spawn_blocking(move || integrity::verify_library(check_hashes))
+        .await
+        .map_err(|e| format!("检查图片库任务失败: {}", e))?
+}
+
+/// 执行图片库修复操作
+#[command]
+pub async fn repair_library(actions: Vec<RepairAction>) -> Result<RepairReport, String> {
+    tauri::async_runtime::spawn_blocking(move || integrity::repair_library(&actions))
+        .await
+        .map_err(|e| format!("修复图片库任务失败: {}", e))?
+}
+
+/// 立即校验最久未校验的图片哈希，limit 默认为每日额度
+#[command]
+pub async fn scrub_library(app: AppHandle, limit: Option<usize>) -> Result<ScrubReport, String> {
+    let limit = limit.unwrap_or(config::load_config().scrub_daily_limit as usize);
+    tauri::async_runtime::spawn_blocking(move || {
+        scrub::scrub_batch(limit, |failure| {
+            let _ = app.emit(scrub::MISMATCH_EVENT, failure);
         })
     })
-    .map_err(|e| format!("查询图片失败: {}", e))?;
-
-    Ok(image)
+    .await
+    .map_err(|e| format!("哈希校验任务失败: {}", e))?
 }
 
-/// 删除图片
+/// 获取图片的哈希校验历史，最近的在前
 #[command]
-pub fn delete_image(id: i32) -> Result<(), String> {
-    use rusqlite::params;
-    use crate::database::get_connection;
+pub fn get_verification_history(id: i32) -> Result<Vec<VerificationRecord>, String> {
+    database::get_verification_history(id).map_err(|e| format!("查询校验记录失败: {}", e))
+}
 
-    let conn = get_connection()
-        .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+/// 备份整个图片库到 dest_dir 下的新目录
+#[command]
+pub async fn backup_library(dest_dir: String) -> Result<BackupReport, String> {
+    tauri::async_runtime::spawn_blocking(move || backup::backup_library(Path::new(&dest_dir)))
+        .await
+        .map_err(|e| format!("备份任务失败: {}", e))?
+}
 
-    // 先获取图片信息
-    let image = get_image_by_id(id)?;
-
-    // 删除数据库记录
-    conn.execute(
-        "DELETE FROM images WHERE id = ?1",
-        params![id],
-    )
-    .map_err(|e| format!("删除数据库记录失败: {}", e))?;
-
-    // 删除文件
-    let file_path = Path::new(&image.path);
-    if file_path.exists() {
-        fs::remove_file(file_path)
-            .map_err(|e| format!("删除文件失败: {}", e))?;
+/// 校验并从备份目录恢复图片库
+#[command]
+pub async fn restore_library(backup_dir: String) -> Result<RestoreReport, String> {
+    let report = tauri::async_runtime::spawn_blocking(move || backup::restore_library(Path::new(&backup_dir)))
+        .await
+        .map_err(|e| format!("恢复任务失败: {}", e))??;
+
+    // 恢复的配置中监视文件夹可能不同
+    if let Err(e) = watcher::restart() {
+        eprintln!("重启文件夹监视失败: {}", e);
     }
+    Ok(report)
+}
 
-    Ok(())
+/// 增量备份到配置的备份目录
+#[command]
+pub async fn run_incremental_backup() -> Result<IncrementalBackupReport, String> {
+    tauri::async_runtime::spawn_blocking(backup::incremental_backup)
+        .await
+        .map_err(|e| format!("增量备份任务失败: {}", e))?
+}
+
+/// 列出增量备份的各个版本，最新的在前
+#[command]
+pub fn list_backup_generations() -> Result<Vec<BackupGeneration>, String> {
+    backup::list_backup_generations()
+}
+
+/// 按当前图片库核对最新的增量备份
+#[command]
+pub async fn verify_incremental_backup() -> Result<BackupVerifyReport, String> {
+    tauri::async_runtime::spawn_blocking(backup::verify_incremental_backup)
+        .await
+        .map_err(|e| format!("校验备份任务失败: {}", e))?
+}
+
+/// 获取图片的标签
+#[command]
+pub fn get_image_tags(id: i32) -> Result<Vec<String>, String> {
+    database::get_image_tags(id).map_err(|e| format!("查询标签失败: {}", e))
+}
+
+/// 为图片添加标签，返回添加后的全部标签
+#[command]
+pub fn add_image_tags(id: i32, tags: Vec<String>) -> Result<Vec<String>, String> {
+    // 确认图片存在
+    get_image_by_id(id)?;
+
+    database::add_tags(&[id], &database::normalize_tags(&tags))
+        .map_err(|e| format!("添加标签失败: {}", e))?;
+
+    get_image_tags(id)
+}
+
+/// 移除图片的标签，返回移除后的全部标签
+#[command]
+pub fn remove_image_tags(id: i32, tags: Vec<String>) -> Result<Vec<String>, String> {
+    database::remove_tags(&[id], &database::normalize_tags(&tags))
+        .map_err(|e| format!("移除标签失败: {}", e))?;
+
+    get_image_tags(id)
+}
+
+/// 批量为多张图片添加标签，返回新增的标签数
+#[command]
+pub fn bulk_tag_images(ids: Vec<i32>, tags: Vec<String>) -> Result<usize, String> {
+    database::add_tags(&ids, &database::normalize_tags(&tags))
+        .map_err(|e| format!("批量添加标签失败: {}", e))
+}
+
+/// 批量移除多张图片的标签，返回删除的标签数
+#[command]
+pub fn bulk_untag_images(ids: Vec<i32>, tags: Vec<String>) -> Result<usize, String> {
+    database::remove_tags(&ids, &database::normalize_tags(&tags))
+        .map_err(|e| format!("批量移除标签失败: {}", e))
+}
+
+/// 重命名标签
+#[command]
+pub fn rename_tag(old_tag: String, new_tag: String) -> Result<usize, String> {
+    let new_tag = database::normalize_tags(&[new_tag])
+        .pop()
+        .ok_or_else(|| "标签不能为空".to_string())?;
+
+    database::rename_tag(old_tag.trim(), &new_tag)
+        .map_err(|e| format!("重命名标签失败: {}", e))
+}
+
+/// 列出所有标签及使用次数，可按前缀过滤
+#[command]
+pub fn list_tags(prefix: Option<String>) -> Result<Vec<TagCount>, String> {
+    let prefix = prefix.as_deref().map(str::trim).filter(|p| !p.is_empty());
+
+    database::list_tags(prefix).map_err(|e| format!("查询标签失败: {}", e))
+}
+
+/// 获取带有指定标签的图片
+#[command]
+pub fn get_images_by_tag(tag: String) -> Result<Vec<ImageInfo>, String> {
+    database::get_images_by_tag(tag.trim())
+        .map(|records| records.into_iter().map(Into::into).collect())
+        .map_err(|e| format!("查询图片失败: {}", e))
 }
 
 /// 启动 API 服务器
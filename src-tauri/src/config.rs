@@ -15,6 +15,59 @@ pub struct AppConfig {
     pub thumbnail_max_width: u32,
     /// 缩略图最大高度
     pub thumbnail_max_height: u32,
+    /// 缩放图（按需生成的不同尺寸版本）缓存目录
+    #[serde(default)]
+    pub renditions_dir: Option<String>,
+    /// 搜索时是否支持拼音（全拼和首字母）匹配
+    #[serde(default = "default_true")]
+    pub search_pinyin: bool,
+    /// 登录密码的 Argon2 哈希（首次运行时以默认密码生成）
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    /// 是否仍在使用默认密码，修改前 API 不接受登录
+    #[serde(default)]
+    pub password_change_required: bool,
+    /// 登录令牌签名密钥（首次运行时随机生成）
+    #[serde(default)]
+    pub token_secret: Option<String>,
+    /// 登录令牌有效期（小时）
+    #[serde(default = "default_token_ttl_hours")]
+    pub token_ttl_hours: u64,
+    /// 监视的文件夹，新增或修改的图片自动导入
+    #[serde(default)]
+    pub watched_folders: Vec<String>,
+    /// 回收站中的图片保留天数，超过后自动永久删除（0 表示不自动删除）
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// 每天后台重新校验哈希的图片数量上限（0 表示不校验）
+    #[serde(default = "default_scrub_daily_limit")]
+    pub scrub_daily_limit: u32,
+    /// 增量备份的目标目录（如挂载的 NAS 或移动硬盘）
+    #[serde(default)]
+    pub backup_target_dir: Option<String>,
+    /// 增量备份保留的版本数量
+    #[serde(default = "default_backup_generations")]
+    pub backup_generations: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_token_ttl_hours() -> u64 {
+    24
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_scrub_daily_limit() -> u32 {
+    500
+}
+
+fn default_backup_generations() -> u32 {
+    7
 }
 
 impl Default for AppConfig {
@@ -25,12 +78,28 @@ impl Default for AppConfig {
             auto_generate_thumbnails: true,
             thumbnail_max_width: 400,
             thumbnail_max_height: 400,
+            renditions_dir: None,
+            search_pinyin: true,
+            password_hash: None,
+            password_change_required: false,
+            token_secret: None,
+            token_ttl_hours: default_token_ttl_hours(),
+            watched_folders: Vec::new(),
+            trash_retention_days: default_trash_retention_days(),
+            scrub_daily_limit: default_scrub_daily_limit(),
+            backup_target_dir: None,
+            backup_generations: default_backup_generations(),
         }
     }
 }
 
 /// 获取配置文件路径
 pub fn get_config_path() -> PathBuf {
+    #[cfg(test)]
+    if let Some(root) = crate::test_support::root() {
+        return root.join("config.json");
+    }
+
     if cfg!(debug_assertions) {
         // 开发模式：使用项目根目录
         let mut path = std::env::current_exe().unwrap();
@@ -159,3 +228,25 @@ pub fn get_thumbnails_dir() -> PathBuf {
     path.push("thumbnails");
     path
 }
+
+/// 获取缩放图缓存目录
+pub fn get_renditions_dir() -> PathBuf {
+    let config = load_config();
+
+    if let Some(dir) = config.renditions_dir {
+        let path = PathBuf::from(&dir);
+        if path.is_absolute() {
+            return path;
+        }
+
+        let config_path = get_config_path();
+        if let Some(parent) = config_path.parent() {
+            return parent.join(path);
+        }
+    }
+
+    // 默认：使用图片目录下的 renditions 子目录
+    let mut path = get_images_dir();
+    path.push("renditions");
+    path
+}
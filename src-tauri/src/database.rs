@@ -1,8 +1,21 @@
-use rusqlite::{Connection, Result as SqliteResult};
+use crate::config;
+use crate::search::{self, SearchQuery};
+use crate::upload::calculate_file_hash;
+use rusqlite::types::Value;
+use rusqlite::{
+    params, params_from_iter, Connection, OptionalExtension, Result as SqliteResult, Row, Transaction,
+    TransactionBehavior,
+};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::fs;
 
 pub fn get_database_path() -> PathBuf {
+    #[cfg(test)]
+    if let Some(root) = crate::test_support::root() {
+        return root.join("dev.db");
+    }
+
     // 在开发模式下，使用项目根目录的 dev.db
     // 在生产模式下，使用应用数据目录
     if cfg!(debug_assertions) {
@@ -57,10 +70,125 @@ pub fn get_connection() -> SqliteResult<Connection> {
         }
     }
 
-    Connection::open(&db_path)
+    let conn = Connection::open(&db_path)?;
+
+    // 批量导入等场景会有多个连接同时写入，等待锁释放而不是立即报错
+    conn.busy_timeout(std::time::Duration::from_secs(10))?;
+
+    // 启用外键约束，删除图片时级联删除元数据和标签
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
+    Ok(conn)
+}
+
+/// 维护 images_fts 的所有触发器
+const FTS_TRIGGERS: [&str; 9] = [
+    "images_fts_insert",
+    "images_fts_delete",
+    "images_fts_update",
+    "image_tags_fts_insert",
+    "image_tags_fts_delete",
+    "image_tags_fts_update",
+    "image_metadata_fts_insert",
+    "image_metadata_fts_delete",
+    "image_metadata_fts_update",
+];
+
+/// 生成把图片加入待索引队列的 SQL，id_expr 为图片 ID 表达式（如 NEW.id）
+///
+/// 触发器只用普通 SQL 记录变化的图片，分词和拼音由 sync_search_index 在 Rust 中计算，
+/// 其他程序（Node 服务器、sqlite3 命令行等）写入数据库时不依赖自定义函数。
+fn fts_enqueue_sql(id_expr: &str) -> String {
+    format!(
+        "INSERT OR IGNORE INTO images_fts_pending(image_id) VALUES ({})",
+        id_expr
+    )
+}
+
+/// 重建待索引队列中图片的索引行：先删除旧行，图片仍存在时按当前内容写入新行
+///
+/// 文本先经 search::segment_cjk 将中日韩文字切分为二元词，拼音列由 search::pinyin_tokens 生成。
+/// 搜索前调用，其他程序写入的变化也会在下次搜索时补上索引。
+pub fn sync_search_index(conn: &Connection) -> SqliteResult<()> {
+    let pending: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM images_fts_pending)",
+        [],
+        |row| row.get(0),
+    )?;
+    if !pending {
+        return Ok(());
+    }
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    {
+        let mut select = tx.prepare(
+            "SELECT p.image_id,
+                i.filename,
+                COALESCE(i.description, ''),
+                COALESCE((SELECT group_concat(t.tag, ' ') FROM image_tags t WHERE t.image_id = i.id), ''),
+                COALESCE((SELECT trim(COALESCE(m.exif_make, '') || ' ' || COALESCE(m.exif_model, '') || ' ' || COALESCE(m.description, ''))
+                    FROM image_metadata m WHERE m.image_id = i.id), '')
+            FROM images_fts_pending p
+            LEFT JOIN images i ON i.id = p.image_id",
+        )?;
+        let mut delete = tx.prepare("DELETE FROM images_fts WHERE rowid = ?1")?;
+        let mut insert = tx.prepare(
+            "INSERT INTO images_fts(rowid, filename, description, tags, exif, pinyin)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            delete.execute([id])?;
+
+            // 图片已删除时只删除索引行
+            let Some(filename) = row.get::<_, Option<String>>(1)? else {
+                continue;
+            };
+            let description: String = row.get(2)?;
+            let tags: String = row.get(3)?;
+            let exif: String = row.get(4)?;
+            let pinyin = if search::pinyin_enabled() {
+                search::pinyin_tokens(&format!("{} {} {}", filename, description, tags))
+            } else {
+                String::new()
+            };
+
+            insert.execute(params![
+                id,
+                search::segment_cjk(&filename),
+                search::segment_cjk(&description),
+                search::segment_cjk(&tags),
+                search::segment_cjk(&exif),
+                pinyin,
+            ])?;
+        }
+    }
+    tx.execute("DELETE FROM images_fts_pending", [])?;
+    tx.commit()
+}
+
+/// 为已有表补充新增的列（CREATE TABLE IF NOT EXISTS 不会修改旧表结构）
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
 }
 
 pub fn init_database() -> SqliteResult<()> {
+    search::set_pinyin_enabled(config::load_config().search_pinyin);
     let conn = get_connection()?;
 
     // 创建 images 表
@@ -100,6 +228,46 @@ pub fn init_database() -> SqliteResult<()> {
         [],
     )?;
 
+    // 拍摄时间列（旧数据库中没有）
+    add_column_if_missing(&conn, "image_metadata", "exif_datetime", "TEXT")?;
+
+    // 按文件内容识别的 MIME 类型（旧数据为空，读取时按文件内容识别）
+    add_column_if_missing(&conn, "images", "mime_type", "TEXT")?;
+
+    // 图片尺寸（按 EXIF 方向旋转后的显示尺寸），旧数据由 backfill_dimensions 补充
+    add_column_if_missing(&conn, "images", "width", "INTEGER")?;
+    add_column_if_missing(&conn, "images", "height", "INTEGER")?;
+    add_column_if_missing(&conn, "images", "aspect_ratio", "REAL")?;
+
+    // 移入回收站的时间，为空表示未删除
+    add_column_if_missing(&conn, "images", "deleted_at", "DATETIME")?;
+
+    // RAW 文件关联的同名 JPEG（主图），删除 JPEG 后 RAW 重新单独显示
+    add_column_if_missing(
+        &conn,
+        "images",
+        "paired_image_id",
+        "INTEGER REFERENCES images(id) ON DELETE SET NULL",
+    )?;
+
+    // 定期哈希校验的最近时间和结果（ok / mismatch / missing / error）
+    add_column_if_missing(&conn, "images", "verified_at", "DATETIME")?;
+    add_column_if_missing(&conn, "images", "verify_status", "TEXT")?;
+
+    // 每次哈希校验的历史记录
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_verifications (
+            id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            image_id INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            actual_hash TEXT,
+            error TEXT,
+            verified_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (image_id) REFERENCES images(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     // 创建 image_tags 表
     conn.execute(
         "CREATE TABLE IF NOT EXISTS image_tags (
@@ -113,65 +281,144 @@ pub fn init_database() -> SqliteResult<()> {
     )?;
 
     // 创建索引
+    // 列表排序使用的索引
     conn.execute(
-        "CREATE INDEX IF NOT EXISTS image_tags_image_id_idx ON image_tags(image_id)",
+        "CREATE INDEX IF NOT EXISTS images_created_at_idx ON images(created_at)",
         [],
     )?;
     conn.execute(
-        "CREATE INDEX IF NOT EXISTS image_tags_tag_idx ON image_tags(tag)",
+        "CREATE INDEX IF NOT EXISTS images_filename_idx ON images(filename COLLATE NOCASE)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_size_idx ON images(size)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_aspect_ratio_idx ON images(aspect_ratio)",
+        [],
+    )?;
+    // 列表排除已配对 RAW、查找 JPEG 对应的 RAW 时使用
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_paired_image_id_idx ON images(paired_image_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_deleted_at_idx ON images(deleted_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS images_verified_at_idx ON images(verified_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS image_verifications_image_id_idx ON image_verifications(image_id)",
         [],
     )?;
 
-    // 删除旧的 FTS 表和触发器（如果存在）
-    let _ = conn.execute("DROP TRIGGER IF EXISTS images_fts_insert", []);
-    let _ = conn.execute("DROP TRIGGER IF EXISTS images_fts_delete", []);
-    let _ = conn.execute("DROP TRIGGER IF EXISTS images_fts_update", []);
-    let _ = conn.execute("DROP TABLE IF EXISTS images_fts", []);
-
-    // 创建 FTS5 全文搜索表
+    // 监视文件夹中已处理过的文件，启动补扫时跳过未变化的文件
     conn.execute(
-        "CREATE VIRTUAL TABLE IF NOT EXISTS images_fts USING fts5(
-            filename,
-            description
+        "CREATE TABLE IF NOT EXISTS watched_files (
+            path TEXT NOT NULL PRIMARY KEY,
+            size INTEGER NOT NULL,
+            modified INTEGER NOT NULL
         )",
         [],
     )?;
 
-    // 重新填充 FTS 表
     conn.execute(
-        "INSERT INTO images_fts(rowid, filename, description)
-        SELECT id, filename, COALESCE(description, '') FROM images",
+        "CREATE INDEX IF NOT EXISTS image_tags_image_id_idx ON image_tags(image_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS image_tags_tag_idx ON image_tags(tag)",
         [],
     )?;
 
-    // 创建 FTS 触发器
+    // 同一图片的标签不重复：先清理旧数据中的重复项，再建唯一索引
     conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS images_fts_insert AFTER INSERT ON images BEGIN
-            INSERT INTO images_fts(rowid, filename, description)
-            VALUES (NEW.id, NEW.filename, COALESCE(NEW.description, ''));
-        END",
+        "DELETE FROM image_tags WHERE id NOT IN (
+            SELECT MIN(id) FROM image_tags GROUP BY image_id, tag
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS image_tags_image_id_tag_key ON image_tags(image_id, tag)",
         [],
     )?;
 
+    // 删除旧的 FTS 表和触发器（如果存在）
+    for trigger in FTS_TRIGGERS {
+        let _ = conn.execute(&format!("DROP TRIGGER IF EXISTS {}", trigger), []);
+    }
+    let _ = conn.execute("DROP TABLE IF EXISTS images_fts", []);
+
+    // 创建 FTS5 全文搜索表：文件名、描述、标签、EXIF 文本（相机、镜头描述等）和拼音
     conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS images_fts_delete AFTER DELETE ON images BEGIN
-            DELETE FROM images_fts WHERE rowid = OLD.id;
-        END",
+        "CREATE VIRTUAL TABLE IF NOT EXISTS images_fts USING fts5(
+            filename,
+            description,
+            tags,
+            exif,
+            pinyin
+        )",
         [],
     )?;
 
+    // 待重建索引的图片（由触发器写入，已删除的图片也保留在队列中以便删除索引行）
     conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS images_fts_update AFTER UPDATE ON images BEGIN
-            DELETE FROM images_fts WHERE rowid = OLD.id;
-            INSERT INTO images_fts(rowid, filename, description)
-            VALUES (NEW.id, NEW.filename, COALESCE(NEW.description, ''));
-        END",
+        "CREATE TABLE IF NOT EXISTS images_fts_pending (
+            image_id INTEGER NOT NULL PRIMARY KEY
+        )",
         [],
     )?;
 
+    // 创建 FTS 触发器：images、image_tags、image_metadata 变化时把对应图片加入待索引队列
+    for (name, event, table, id_expr) in [
+        ("images_fts_insert", "INSERT", "images", "NEW.id"),
+        ("images_fts_delete", "DELETE", "images", "OLD.id"),
+        ("images_fts_update", "UPDATE OF filename, description", "images", "NEW.id"),
+        ("image_tags_fts_insert", "INSERT", "image_tags", "NEW.image_id"),
+        ("image_tags_fts_delete", "DELETE", "image_tags", "OLD.image_id"),
+        ("image_tags_fts_update", "UPDATE", "image_tags", "NEW.image_id"),
+        ("image_metadata_fts_insert", "INSERT", "image_metadata", "NEW.image_id"),
+        ("image_metadata_fts_delete", "DELETE", "image_metadata", "OLD.image_id"),
+        ("image_metadata_fts_update", "UPDATE", "image_metadata", "NEW.image_id"),
+    ] {
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS {} AFTER {} ON {} BEGIN
+                    {};
+                END",
+                name, event, table, fts_enqueue_sql(id_expr)
+            ),
+            [],
+        )?;
+    }
+
+    // 重新填充 FTS 表
+    conn.execute("INSERT OR IGNORE INTO images_fts_pending(image_id) SELECT id FROM images", [])?;
+    sync_search_index(&conn)?;
+
     Ok(())
 }
 
+/// 图片 EXIF 元数据（image_metadata 表）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub exif_make: Option<String>,
+    pub exif_model: Option<String>,
+    pub exif_iso: Option<i64>,
+    pub exif_aperture: Option<f64>,
+    pub exif_exposure_time: Option<String>,
+    /// 拍摄时间，格式为 "YYYY-MM-DD HH:MM:SS"
+    pub exif_datetime: Option<String>,
+    pub gps_latitude: Option<f64>,
+    pub gps_longitude: Option<f64>,
+    pub gps_altitude: Option<f64>,
+    pub description: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct ImageRecord {
     pub id: i32,
@@ -179,10 +426,98 @@ pub struct ImageRecord {
     pub path: String,
     pub thumbnail_path: Option<String>,
     pub size: i64,
-    #[allow(dead_code)]
     pub hash: String,
     pub description: Option<String>,
     pub created_at: String,
+    /// 按文件内容识别的 MIME 类型
+    pub mime_type: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 宽高比（宽 / 高）
+    pub aspect_ratio: Option<f64>,
+    /// RAW+JPEG 配对中另一张图片的 ID
+    pub paired_id: Option<i32>,
+    /// 移入回收站的时间
+    pub deleted_at: Option<String>,
+    /// 最近一次哈希校验的时间和结果
+    pub verified_at: Option<String>,
+    pub verify_status: Option<String>,
+    pub metadata: Option<ImageMetadata>,
+    pub tags: Vec<String>,
+    /// 搜索命中时的高亮摘要（仅搜索结果有值）：原文已 HTML 转义，命中部分用 <mark> 包围
+    pub snippet: Option<String>,
+}
+
+/// 标签及其使用次数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// 单个标签的最大长度（字符数）
+const MAX_TAG_LENGTH: usize = 64;
+
+/// group_concat 拼接标签时使用的分隔符（ASCII 单元分隔符，不会出现在标签中）
+const TAG_SEPARATOR: char = '\u{1f}';
+
+/// 查询图片记录的公共列（images 别名 i，image_metadata 别名 m），配合 IMAGE_TABLES 使用
+const IMAGE_COLUMNS: &str = "i.id, i.filename, i.path, i.thumbnail_path, i.size, i.hash, i.description, i.created_at,
+        m.image_id, m.exif_make, m.exif_model, m.exif_iso, m.exif_aperture, m.exif_exposure_time,
+        m.exif_datetime, m.gps_latitude, m.gps_longitude, m.gps_altitude, m.description,
+        (SELECT group_concat(t.tag, char(31)) FROM image_tags t WHERE t.image_id = i.id),
+        i.mime_type, i.width, i.height, i.aspect_ratio,
+        COALESCE(i.paired_image_id, (SELECT p.id FROM images p WHERE p.paired_image_id = i.id)),
+        i.deleted_at, i.verified_at, i.verify_status";
+
+const IMAGE_TABLES: &str = "images i LEFT JOIN image_metadata m ON m.image_id = i.id";
+
+/// 将 IMAGE_COLUMNS 查询结果的一行转换为 ImageRecord
+fn map_image_row(row: &Row) -> SqliteResult<ImageRecord> {
+    let metadata_id: Option<i32> = row.get(8)?;
+    let metadata = match metadata_id {
+        Some(_) => Some(ImageMetadata {
+            exif_make: row.get(9)?,
+            exif_model: row.get(10)?,
+            exif_iso: row.get(11)?,
+            exif_aperture: row.get(12)?,
+            exif_exposure_time: row.get(13)?,
+            exif_datetime: row.get(14)?,
+            gps_latitude: row.get(15)?,
+            gps_longitude: row.get(16)?,
+            gps_altitude: row.get(17)?,
+            description: row.get(18)?,
+        }),
+        None => None,
+    };
+
+    let tags_concat: Option<String> = row.get(19)?;
+    let mut tags: Vec<String> = tags_concat
+        .map(|s| s.split(TAG_SEPARATOR).map(String::from).collect())
+        .unwrap_or_default();
+    tags.sort();
+
+    Ok(ImageRecord {
+        id: row.get(0)?,
+        filename: row.get(1)?,
+        path: row.get(2)?,
+        thumbnail_path: row.get(3)?,
+        size: row.get(4)?,
+        hash: row.get(5)?,
+        description: row.get(6)?,
+        created_at: row.get(7)?,
+        mime_type: row.get(20)?,
+        width: row.get(21)?,
+        height: row.get(22)?,
+        aspect_ratio: row.get(23)?,
+        paired_id: row.get(24)?,
+        deleted_at: row.get(25)?,
+        verified_at: row.get(26)?,
+        verify_status: row.get(27)?,
+        metadata,
+        tags,
+        snippet: None,
+    })
 }
 
 pub fn insert_image(
@@ -190,13 +525,14 @@ pub fn insert_image(
     path: &str,
     size: i64,
     hash: &str,
+    mime_type: &str,
 ) -> SqliteResult<i32> {
     let conn = get_connection()?;
 
     // 使用 datetime('now') 为 updated_at 提供当前时间
     conn.execute(
-        "INSERT INTO images (filename, path, size, hash, updated_at) VALUES (?1, ?2, ?3, ?4, datetime('now'))",
-        &[filename, path, &size.to_string(), hash],
+        "INSERT INTO images (filename, path, size, hash, mime_type, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+        params![filename, path, size, hash, mime_type],
     )?;
 
     Ok(conn.last_insert_rowid() as i32)
@@ -205,62 +541,757 @@ pub fn insert_image(
 pub fn get_all_images() -> SqliteResult<Vec<ImageRecord>> {
     let conn = get_connection()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, filename, path, thumbnail_path, size, hash, description, created_at
-         FROM images
-         ORDER BY created_at DESC"
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE i.deleted_at IS NULL ORDER BY i.created_at DESC",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+
+    let images = stmt.query_map([], map_image_row)?;
+
+    images.collect()
+}
+
+/// 更新图片的缩略图路径
+pub fn update_thumbnail_path(id: i32, thumbnail_path: Option<&str>) -> SqliteResult<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE images SET thumbnail_path = ?1 WHERE id = ?2",
+        params![thumbnail_path, id],
     )?;
 
-    let images = stmt.query_map([], |row| {
-        Ok(ImageRecord {
+    Ok(())
+}
+
+/// 更新图片尺寸，同时计算宽高比
+pub fn update_image_dimensions(id: i32, width: u32, height: u32) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE images SET width = ?1, height = ?2, aspect_ratio = ?3 WHERE id = ?4",
+        params![width, height, width as f64 / height as f64, id],
+    )?;
+    Ok(())
+}
+
+/// 查询文件名以 basename 加扩展名开头、且尚未配对的其他图片（basename 为小写）
+pub fn find_unpaired_images_by_basename(exclude_id: i32, basename: &str) -> SqliteResult<Vec<ImageRecord>> {
+    let conn = get_connection()?;
+    let pattern = format!(
+        "{}.%",
+        basename.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    );
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {}
+         WHERE i.id != ?1 AND lower(i.filename) LIKE ?2 ESCAPE '\\'
+           AND i.paired_image_id IS NULL AND i.deleted_at IS NULL
+           AND NOT EXISTS (SELECT 1 FROM images p WHERE p.paired_image_id = i.id)
+         ORDER BY i.id",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+    let rows = stmt.query_map(params![exclude_id, pattern], map_image_row)?;
+    rows.collect()
+}
+
+/// 将 RAW 记录关联到同名 JPEG
+pub fn set_paired_image(raw_id: i32, jpeg_id: i32) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE images SET paired_image_id = ?1 WHERE id = ?2",
+        params![jpeg_id, raw_id],
+    )?;
+    Ok(())
+}
+
+/// 将图片标记为已删除，path 为移入回收站后的文件路径
+pub fn mark_image_trashed(id: i32, path: &str) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE images SET deleted_at = datetime('now'), path = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![path, id],
+    )?;
+    Ok(())
+}
+
+/// 从回收站恢复图片，path 为恢复后的文件路径
+pub fn mark_image_restored(id: i32, path: &str) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE images SET deleted_at = NULL, path = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![path, id],
+    )?;
+    Ok(())
+}
+
+/// 更新图片文件路径（如文件被移入隔离目录后）
+pub fn update_image_path(id: i32, path: &str) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "UPDATE images SET path = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![path, id],
+    )?;
+    Ok(())
+}
+
+/// 查询回收站中的图片，retention_days 不为空时只返回删除超过该天数的图片
+pub fn get_trashed_images(retention_days: Option<u32>) -> SqliteResult<Vec<ImageRecord>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {}
+         WHERE i.deleted_at IS NOT NULL
+           AND (?1 IS NULL OR i.deleted_at <= datetime('now', '-' || ?1 || ' days'))
+         ORDER BY i.deleted_at DESC",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+    let rows = stmt.query_map([retention_days], map_image_row)?;
+    rows.collect()
+}
+
+/// 删除图片记录（元数据和标签级联删除）
+pub fn delete_image(id: i32) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM images WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// 迁移：旧版本重命名时会改动磁盘文件名，将这些文件移回按哈希命名的存储路径
+///
+/// 返回迁移的文件数量，文件缺失的记录跳过（留给库校验处理）
+pub fn migrate_renamed_files() -> Result<usize, String> {
+    let conn = get_connection()
+        .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+
+    let rows: Vec<(i32, String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, path, hash FROM images WHERE deleted_at IS NULL")
+            .map_err(|e| format!("查询图片失败: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| format!("查询图片失败: {}", e))?;
+        rows.collect::<SqliteResult<_>>()
+            .map_err(|e| format!("读取图片记录失败: {}", e))?
+    };
+
+    let mut migrated = 0;
+    for (id, path, hash) in rows {
+        let current = PathBuf::from(&path);
+        if current.file_stem().and_then(|s| s.to_str()) == Some(hash.as_str()) || !current.exists() {
+            continue;
+        }
+
+        let extension = current
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| format!(".{}", e))
+            .unwrap_or_default();
+        let target = get_image_storage_path(&hash, &extension);
+
+        if target.exists() {
+            // 规范路径上的文件内容与记录一致时，改名后的副本才是重复文件；
+            // 否则（截断或无关的文件）两者都保留，记录继续指向改名后的文件
+            match calculate_file_hash(&target) {
+                Ok(target_hash) if target_hash == hash => {
+                    fs::remove_file(&current)
+                        .map_err(|e| format!("删除重复文件失败 {}: {}", path, e))?;
+                }
+                Ok(_) => {
+                    eprintln!("跳过迁移 {}: {} 的内容与记录的哈希不一致", path, target.display());
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("跳过迁移 {}: {}", path, e);
+                    continue;
+                }
+            }
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("创建目录失败: {}", e))?;
+            }
+            if fs::rename(&current, &target).is_err() {
+                fs::copy(&current, &target)
+                    .map_err(|e| format!("移动文件失败 {}: {}", path, e))?;
+                fs::remove_file(&current).ok();
+            }
+        }
+
+        conn.execute(
+            "UPDATE images SET path = ?1 WHERE id = ?2",
+            params![target.to_string_lossy(), id],
+        )
+        .map_err(|e| format!("更新图片路径失败: {}", e))?;
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
+
+/// 查询待哈希校验的图片：从未校验的优先，其次是最久未校验的
+pub fn get_images_to_verify(limit: usize) -> SqliteResult<Vec<ImageRecord>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE i.deleted_at IS NULL ORDER BY i.verified_at, i.id LIMIT ?1",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+    let rows = stmt.query_map([limit as i64], map_image_row)?;
+    rows.collect()
+}
+
+/// 最近 24 小时内校验过的图片数量
+pub fn count_recently_verified() -> SqliteResult<i64> {
+    let conn = get_connection()?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM images WHERE verified_at >= datetime('now', '-1 day')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// 记录一次哈希校验：写入历史记录，并更新图片的最近校验时间和结果
+pub fn record_verification(
+    id: i32,
+    status: &str,
+    actual_hash: Option<&str>,
+    error: Option<&str>,
+) -> SqliteResult<()> {
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO image_verifications (image_id, status, actual_hash, error) VALUES (?1, ?2, ?3, ?4)",
+        params![id, status, actual_hash, error],
+    )?;
+    tx.execute(
+        "UPDATE images SET verified_at = datetime('now'), verify_status = ?1 WHERE id = ?2",
+        params![status, id],
+    )?;
+    tx.commit()
+}
+
+/// 哈希校验历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationRecord {
+    pub id: i64,
+    pub image_id: i32,
+    pub status: String,
+    pub actual_hash: Option<String>,
+    pub error: Option<String>,
+    pub verified_at: String,
+}
+
+/// 查询图片的哈希校验历史，最近的在前
+pub fn get_verification_history(image_id: i32) -> SqliteResult<Vec<VerificationRecord>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, image_id, status, actual_hash, error, verified_at
+         FROM image_verifications WHERE image_id = ?1 ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map([image_id], |row| {
+        Ok(VerificationRecord {
             id: row.get(0)?,
-            filename: row.get(1)?,
-            path: row.get(2)?,
-            thumbnail_path: row.get(3)?,
-            size: row.get(4)?,
-            hash: row.get(5)?,
-            description: row.get(6)?,
-            created_at: row.get(7)?,
+            image_id: row.get(1)?,
+            status: row.get(2)?,
+            actual_hash: row.get(3)?,
+            error: row.get(4)?,
+            verified_at: row.get(5)?,
         })
     })?;
+    rows.collect()
+}
 
-    images.collect()
+/// 查询缺少尺寸信息的图片
+pub fn get_images_without_dimensions() -> SqliteResult<Vec<ImageRecord>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE i.width IS NULL OR i.height IS NULL ORDER BY i.id",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+    let rows = stmt.query_map([], map_image_row)?;
+    rows.collect()
 }
 
-pub fn image_exists_by_hash(hash: &str) -> SqliteResult<bool> {
+/// 根据 ID 获取图片记录
+pub fn get_image_by_id(id: i32) -> SqliteResult<ImageRecord> {
     let conn = get_connection()?;
-    let mut stmt = conn.prepare("SELECT COUNT(*) FROM images WHERE hash = ?1")?;
 
-    let count: i64 = stmt.query_row([hash], |row| row.get(0))?;
-    Ok(count > 0)
+    conn.query_row(
+        &format!("SELECT {} FROM {} WHERE i.id = ?1", IMAGE_COLUMNS, IMAGE_TABLES),
+        [id],
+        map_image_row,
+    )
 }
 
-/// 使用 FTS5 搜索图片
-pub fn search_images(query: &str) -> SqliteResult<Vec<ImageRecord>> {
+/// 写入图片 EXIF 元数据（已存在则覆盖）
+pub fn upsert_image_metadata(image_id: i32, metadata: &ImageMetadata) -> SqliteResult<()> {
     let conn = get_connection()?;
 
-    // 使用简单的 LIKE 搜索替代 FTS，避免兼容性问题
-    let mut stmt = conn.prepare(
-        "SELECT id, filename, path, thumbnail_path, size, hash, description, created_at
-         FROM images
-         WHERE filename LIKE ?1 OR description LIKE ?1
-         ORDER BY created_at DESC"
+    conn.execute(
+        "INSERT INTO image_metadata (
+            image_id, exif_make, exif_model, exif_iso, exif_aperture, exif_exposure_time,
+            exif_datetime, gps_latitude, gps_longitude, gps_altitude, description
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT(image_id) DO UPDATE SET
+            exif_make = excluded.exif_make,
+            exif_model = excluded.exif_model,
+            exif_iso = excluded.exif_iso,
+            exif_aperture = excluded.exif_aperture,
+            exif_exposure_time = excluded.exif_exposure_time,
+            exif_datetime = excluded.exif_datetime,
+            gps_latitude = excluded.gps_latitude,
+            gps_longitude = excluded.gps_longitude,
+            gps_altitude = excluded.gps_altitude,
+            description = excluded.description",
+        params![
+            image_id,
+            metadata.exif_make,
+            metadata.exif_model,
+            metadata.exif_iso,
+            metadata.exif_aperture,
+            metadata.exif_exposure_time,
+            metadata.exif_datetime,
+            metadata.gps_latitude,
+            metadata.gps_longitude,
+            metadata.gps_altitude,
+            metadata.description,
+        ],
     )?;
 
-    let search_pattern = format!("%{}%", query);
+    Ok(())
+}
 
-    let images = stmt.query_map([&search_pattern], |row| {
-        Ok(ImageRecord {
-            id: row.get(0)?,
-            filename: row.get(1)?,
-            path: row.get(2)?,
-            thumbnail_path: row.get(3)?,
-            size: row.get(4)?,
-            hash: row.get(5)?,
-            description: row.get(6)?,
-            created_at: row.get(7)?,
+/// 规范化标签：去除首尾空白、去掉空标签和重复标签，并限制长度
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+
+    for tag in tags {
+        let tag: String = tag
+            .trim()
+            .chars()
+            .filter(|c| *c != TAG_SEPARATOR)
+            .take(MAX_TAG_LENGTH)
+            .collect();
+        if !tag.is_empty() && !result.contains(&tag) {
+            result.push(tag);
+        }
+    }
+
+    result
+}
+
+/// 获取图片的所有标签
+pub fn get_image_tags(image_id: i32) -> SqliteResult<Vec<String>> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare("SELECT tag FROM image_tags WHERE image_id = ?1 ORDER BY tag")?;
+
+    let tags = stmt.query_map([image_id], |row| row.get(0))?;
+    tags.collect()
+}
+
+/// 为多张图片添加标签（已存在的标签和不存在的图片会被忽略），返回新增的标签数
+pub fn add_tags(image_ids: &[i32], tags: &[String]) -> SqliteResult<usize> {
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+    let mut added = 0;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO image_tags (image_id, tag)
+             SELECT id, ?2 FROM images WHERE id = ?1"
+        )?;
+        for image_id in image_ids {
+            for tag in tags {
+                added += stmt.execute(params![image_id, tag])?;
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(added)
+}
+
+/// 移除多张图片的标签，返回删除的标签数
+pub fn remove_tags(image_ids: &[i32], tags: &[String]) -> SqliteResult<usize> {
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+    let mut removed = 0;
+
+    {
+        let mut stmt = tx.prepare("DELETE FROM image_tags WHERE image_id = ?1 AND tag = ?2")?;
+        for image_id in image_ids {
+            for tag in tags {
+                removed += stmt.execute(params![image_id, tag])?;
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(removed)
+}
+
+/// 重命名标签（图片已有新标签时合并），返回受影响的图片数
+pub fn rename_tag(old_tag: &str, new_tag: &str) -> SqliteResult<usize> {
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+
+    let renamed = tx.execute(
+        "UPDATE OR IGNORE image_tags SET tag = ?2 WHERE tag = ?1",
+        params![old_tag, new_tag],
+    )?;
+    // 剩下的是与新标签冲突的记录，直接删除
+    let merged = tx.execute("DELETE FROM image_tags WHERE tag = ?1", [old_tag])?;
+
+    tx.commit()?;
+    Ok(renamed + merged)
+}
+
+/// 列出所有标签及使用次数，可按前缀过滤
+pub fn list_tags(prefix: Option<&str>) -> SqliteResult<Vec<TagCount>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT t.tag, COUNT(*) AS count
+         FROM image_tags t
+         JOIN images i ON i.id = t.image_id
+         WHERE i.deleted_at IS NULL AND (?1 IS NULL OR substr(t.tag, 1, length(?1)) = ?1)
+         GROUP BY t.tag
+         ORDER BY count DESC, tag"
+    )?;
+
+    let tags = stmt.query_map([prefix], |row| {
+        Ok(TagCount {
+            tag: row.get(0)?,
+            count: row.get(1)?,
         })
     })?;
 
+    tags.collect()
+}
+
+/// 获取带有指定标签的图片
+pub fn get_images_by_tag(tag: &str) -> SqliteResult<Vec<ImageRecord>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {}
+         WHERE i.id IN (SELECT image_id FROM image_tags WHERE tag = ?1) AND i.deleted_at IS NULL
+         ORDER BY i.created_at DESC",
+        IMAGE_COLUMNS, IMAGE_TABLES
+    ))?;
+
+    let images = stmt.query_map([tag], map_image_row)?;
+
     images.collect()
 }
+
+/// 按哈希查找图片（包括回收站中的图片）
+pub fn get_image_by_hash(hash: &str) -> SqliteResult<Option<ImageRecord>> {
+    let conn = get_connection()?;
+
+    conn.query_row(
+        &format!("SELECT {} FROM {} WHERE i.hash = ?1", IMAGE_COLUMNS, IMAGE_TABLES),
+        [hash],
+        map_image_row,
+    )
+    .optional()
+}
+
+/// 监视文件夹中的文件是否已按当前大小和修改时间处理过
+pub fn is_watched_file_processed(path: &str, size: i64, modified: i64) -> SqliteResult<bool> {
+    let conn = get_connection()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM watched_files WHERE path = ?1 AND size = ?2 AND modified = ?3",
+        params![path, size, modified],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// 记录监视文件夹中已处理的文件
+pub fn record_watched_file(path: &str, size: i64, modified: i64) -> SqliteResult<()> {
+    let conn = get_connection()?;
+    conn.execute(
+        "INSERT INTO watched_files (path, size, modified) VALUES (?1, ?2, ?3)
+         ON CONFLICT(path) DO UPDATE SET size = excluded.size, modified = excluded.modified",
+        params![path, size, modified],
+    )?;
+    Ok(())
+}
+
+/// 重建全文索引（修改拼音搜索设置后需要重建）
+pub fn rebuild_search_index() -> SqliteResult<()> {
+    let conn = get_connection()?;
+
+    conn.execute("DELETE FROM images_fts", [])?;
+    conn.execute("INSERT OR IGNORE INTO images_fts_pending(image_id) SELECT id FROM images", [])?;
+    sync_search_index(&conn)?;
+
+    Ok(())
+}
+
+/// 列表排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// 文件名（不区分大小写）
+    Name,
+    /// 文件大小
+    Size,
+    /// 导入时间
+    Created,
+    /// 拍摄时间（没有 EXIF 拍摄时间时使用导入时间）
+    Taken,
+    /// 搜索相关度（仅在有全文检索词时有效）
+    Relevance,
+}
+
+impl SortKey {
+    fn sql_expr(&self) -> &'static str {
+        match self {
+            SortKey::Name => "i.filename COLLATE NOCASE",
+            SortKey::Size => "i.size",
+            SortKey::Created => "i.created_at",
+            SortKey::Taken => "COALESCE(m.exif_datetime, i.created_at)",
+            // bm25 权重依次对应 filename、description、tags、exif、pinyin
+            SortKey::Relevance => "bm25(images_fts, 10.0, 4.0, 6.0, 2.0, 3.0)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// 列表分页与排序参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListOptions {
+    /// 每页数量，None 表示不限制
+    pub limit: Option<i64>,
+    /// 偏移量分页（指定 cursor 时忽略）
+    pub offset: Option<i64>,
+    /// 游标分页：上一页返回的 next_cursor
+    pub cursor: Option<String>,
+    /// 排序字段，默认有全文检索词时按相关度，否则按导入时间
+    pub sort: Option<SortKey>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// 分页游标：上一页最后一条记录的排序值和 ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PageCursor {
+    sort: SortKey,
+    value: serde_json::Value,
+    id: i32,
+}
+
+impl PageCursor {
+    fn encode(&self) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn sql_value(&self) -> Value {
+        match &self.value {
+            serde_json::Value::Number(n) => Value::Integer(n.as_i64().unwrap_or_default()),
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// 一页图片
+#[derive(Debug)]
+pub struct ImagePage {
+    pub images: Vec<ImageRecord>,
+    /// 符合条件的图片总数
+    pub total: i64,
+    /// 下一页游标，没有更多数据时为 None
+    pub next_cursor: Option<String>,
+}
+
+/// 校验分页参数：游标需与排序字段一致，相关度排序只支持偏移量分页
+pub fn validate_list_options(query: &SearchQuery, options: &ListOptions) -> Result<(), String> {
+    let sort = resolve_sort(query, options);
+
+    if sort == SortKey::Relevance && query.fts_query.is_none() {
+        return Err("相关度排序需要提供搜索关键词".to_string());
+    }
+
+    if let Some(cursor) = &options.cursor {
+        if sort == SortKey::Relevance {
+            return Err("相关度排序不支持游标分页，请使用 offset".to_string());
+        }
+        match PageCursor::decode(cursor) {
+            Some(c) if c.sort == sort => {}
+            Some(_) => return Err("游标与排序字段不一致".to_string()),
+            None => return Err("无效的分页游标".to_string()),
+        }
+    }
+
+    if matches!(options.limit, Some(limit) if limit <= 0) {
+        return Err("limit 必须大于 0".to_string());
+    }
+
+    Ok(())
+}
+
+fn resolve_sort(query: &SearchQuery, options: &ListOptions) -> SortKey {
+    options.sort.unwrap_or(if query.fts_query.is_some() {
+        SortKey::Relevance
+    } else {
+        SortKey::Created
+    })
+}
+
+/// 分页查询图片
+///
+/// 有全文检索词时使用 FTS5 匹配并返回高亮摘要；排序字段相同时按 ID 决定先后，
+/// 保证游标分页稳定。调用前应先用 validate_list_options 校验参数。
+pub fn query_images(query: &SearchQuery, options: &ListOptions) -> SqliteResult<ImagePage> {
+    let conn = get_connection()?;
+    sync_search_index(&conn)?;
+    let sort = resolve_sort(query, options);
+
+    let (from_clause, snippet_columns, mut base_params) = match &query.fts_query {
+        Some(fts_query) => (
+            "images_fts
+             JOIN images i ON i.id = images_fts.rowid
+             LEFT JOIN image_metadata m ON m.image_id = i.id"
+                .to_string(),
+            // 摘要逐列生成，取第一个有命中的列（拼音列只用于匹配，不作为摘要）；
+            // 命中位置先用 search::SNIPPET_MARK_START/END（U+E000/U+E001）标记，转义原文后再换成 <mark>
+            "snippet(images_fts, 0, char(57344), char(57345), '…', 12),
+             snippet(images_fts, 1, char(57344), char(57345), '…', 12),
+             snippet(images_fts, 2, char(57344), char(57345), '…', 12),
+             snippet(images_fts, 3, char(57344), char(57345), '…', 12)",
+            vec![Value::Text(fts_query.clone())],
+        ),
+        None => (IMAGE_TABLES.to_string(), "NULL, NULL, NULL, NULL", Vec::new()),
+    };
+    // 不列出回收站中的图片和已与 JPEG 配对的 RAW
+    let base_condition = if query.fts_query.is_some() {
+        "images_fts MATCH ? AND i.deleted_at IS NULL AND i.paired_image_id IS NULL AND "
+    } else {
+        "i.deleted_at IS NULL AND i.paired_image_id IS NULL AND "
+    };
+    base_params.extend(query.params.iter().cloned());
+
+    // 总数
+    let total: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {} WHERE {}{}",
+            from_clause,
+            base_condition,
+            query.where_clause()
+        ),
+        params_from_iter(base_params.iter()),
+        |row| row.get(0),
+    )?;
+
+    let direction = match options.order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    // 相关度：bm25 越小越相关，升序才是最相关在前
+    let (order_clause, cursor_op) = if sort == SortKey::Relevance {
+        (format!("{} ASC, i.created_at DESC, i.id DESC", sort.sql_expr()), "")
+    } else {
+        let op = match options.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        (format!("{} {}, i.id {}", sort.sql_expr(), direction, direction), op)
+    };
+
+    let mut page_params = base_params;
+    let mut cursor_condition = String::new();
+    let cursor = options.cursor.as_deref().and_then(PageCursor::decode);
+    if let Some(cursor) = &cursor {
+        cursor_condition = format!(" AND ({}, i.id) {} (?, ?)", sort.sql_expr(), cursor_op);
+        page_params.push(cursor.sql_value());
+        page_params.push(Value::Integer(cursor.id as i64));
+    }
+
+    let mut limit_clause = String::new();
+    if let Some(limit) = options.limit {
+        limit_clause = " LIMIT ? OFFSET ?".to_string();
+        page_params.push(Value::Integer(limit));
+        let offset = if cursor.is_some() { 0 } else { options.offset.unwrap_or(0).max(0) };
+        page_params.push(Value::Integer(offset));
+    } else if let Some(offset) = options.offset.filter(|o| *o > 0) {
+        limit_clause = " LIMIT -1 OFFSET ?".to_string();
+        page_params.push(Value::Integer(offset));
+    }
+
+    let sort_value_expr = if sort == SortKey::Relevance {
+        "NULL"
+    } else {
+        sort.sql_expr()
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, {}, {}
+         FROM {}
+         WHERE {}{}{}
+         ORDER BY {}{}",
+        IMAGE_COLUMNS,
+        sort_value_expr,
+        snippet_columns,
+        from_clause,
+        base_condition,
+        query.where_clause(),
+        cursor_condition,
+        order_clause,
+        limit_clause
+    ))?;
+
+    let mut last_sort_value = Value::Null;
+    let mut images = Vec::new();
+    let mut rows = stmt.query(params_from_iter(page_params.iter()))?;
+    while let Some(row) = rows.next()? {
+        let mut record = map_image_row(row)?;
+        last_sort_value = row.get(28)?;
+        for index in 29..33 {
+            let snippet: Option<String> = row.get(index)?;
+            if let Some(snippet) = snippet.as_deref().and_then(search::render_snippet) {
+                record.snippet = Some(snippet);
+                break;
+            }
+        }
+        images.push(record);
+    }
+
+    // 取满一页且不是相关度排序时才生成下一页游标
+    let next_cursor = match (options.limit, images.last()) {
+        (Some(limit), Some(last)) if images.len() as i64 >= limit && sort != SortKey::Relevance => {
+            let value = match last_sort_value {
+                Value::Integer(n) => serde_json::Value::from(n),
+                Value::Text(s) => serde_json::Value::from(s),
+                _ => serde_json::Value::Null,
+            };
+            Some(PageCursor { sort, value, id: last.id }.encode())
+        }
+        _ => None,
+    };
+
+    Ok(ImagePage {
+        images,
+        total,
+        next_cursor,
+    })
+}
+
+/// 搜索图片（不分页）
+///
+/// 有全文检索词时按 bm25 相关度排序并返回高亮摘要；只有字段条件时按导入时间倒序返回。
+pub fn search_images(query: &SearchQuery) -> SqliteResult<Vec<ImageRecord>> {
+    query_images(query, &ListOptions::default()).map(|page| page.images)
+}
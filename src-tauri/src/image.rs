@@ -1,5 +1,27 @@
+use crate::raw::{self, RawFormat};
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use image::metadata::Orientation;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 
+/// 识别格式时读取的文件头长度（RAW 的 IFD0 和相机厂商字段都在文件开头）
+const HEADER_SIZE: u64 = 64 * 1024;
+
+/// 需要整体读入内存解码的格式（RAW、HEIF）的文件大小上限
+const MAX_DECODE_IN_MEMORY: u64 = 512 * 1024 * 1024;
+
+/// TIFF 完整性检查最多遍历的 IFD 数量，防止损坏文件中的循环引用
+const MAX_TIFF_IFDS: usize = 64;
+
+const TIFF_STRIP_OFFSETS: u16 = 0x0111;
+const TIFF_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TIFF_TILE_OFFSETS: u16 = 0x0144;
+const TIFF_TILE_BYTE_COUNTS: u16 = 0x0145;
+
+/// 按扩展名判断是否可能是支持的图片，仅用于扫描文件夹时预先筛选，
+/// 导入时以 validate_image_file 识别的实际内容为准
 pub fn validate_image_format(path: &str) -> Result<bool, String> {
     let path_obj = Path::new(path);
     let extension = path_obj
@@ -8,7 +30,660 @@ pub fn validate_image_format(path: &str) -> Result<bool, String> {
         .unwrap_or("");
 
     match extension.to_lowercase().as_str() {
-        "jpg" | "jpeg" | "png" | "webp" => Ok(true),
+        "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" | "tif" | "tiff" | "avif" | "heic"
+        | "heif" | "cr2" | "nef" | "arw" | "dng" => Ok(true),
         _ => Ok(false),
     }
 }
+
+/// 通过文件头识别的源图片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Gif,
+    Bmp,
+    Tiff,
+    Avif,
+    /// HEIC / HEIF
+    Heif,
+    /// 相机 RAW（显示时使用内嵌的 JPEG 预览图）
+    Raw(RawFormat),
+}
+
+impl SourceFormat {
+    /// 根据文件头的魔数识别格式
+    ///
+    /// RAW 与 TIFF 的文件头相同，需要包含 IFD0 及相机厂商字段才能区分，否则识别为 TIFF。
+    pub fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(SourceFormat::Jpeg)
+        } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(SourceFormat::Png)
+        } else if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+            Some(SourceFormat::WebP)
+        } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Some(SourceFormat::Gif)
+        } else if is_bmp_header(header) {
+            Some(SourceFormat::Bmp)
+        } else if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+            Some(raw::detect(header).map_or(SourceFormat::Tiff, SourceFormat::Raw))
+        } else {
+            heif_brand_format(header)
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SourceFormat::Jpeg => "image/jpeg",
+            SourceFormat::Png => "image/png",
+            SourceFormat::WebP => "image/webp",
+            SourceFormat::Gif => "image/gif",
+            SourceFormat::Bmp => "image/bmp",
+            SourceFormat::Tiff => "image/tiff",
+            SourceFormat::Avif => "image/avif",
+            SourceFormat::Heif => "image/heic",
+            SourceFormat::Raw(format) => format.mime_type(),
+        }
+    }
+
+    /// 存储时使用的文件扩展名（含点号）
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SourceFormat::Jpeg => ".jpg",
+            SourceFormat::Png => ".png",
+            SourceFormat::WebP => ".webp",
+            SourceFormat::Gif => ".gif",
+            SourceFormat::Bmp => ".bmp",
+            SourceFormat::Tiff => ".tiff",
+            SourceFormat::Avif => ".avif",
+            SourceFormat::Heif => ".heic",
+            SourceFormat::Raw(format) => format.extension(),
+        }
+    }
+
+    /// 大多数浏览器无法直接显示，需要转换为 JPEG 后再返回
+    pub fn needs_browser_conversion(&self) -> bool {
+        matches!(self, SourceFormat::Heif | SourceFormat::Raw(_))
+    }
+}
+
+/// MIME 类型是否需要转换后才能在浏览器中显示
+pub fn mime_needs_browser_conversion(mime_type: &str) -> bool {
+    matches!(mime_type, "image/heic" | "image/heif") || raw::is_raw_mime(mime_type)
+}
+
+/// BMP 文件头：以 "BM" 开头，信息头长度为已知的几种之一
+fn is_bmp_header(header: &[u8]) -> bool {
+    header.len() >= 18
+        && header.starts_with(b"BM")
+        && matches!(
+            u32::from_le_bytes([header[14], header[15], header[16], header[17]]),
+            12 | 40 | 52 | 56 | 64 | 108 | 124
+        )
+}
+
+/// 按 ISOBMFF ftyp 盒中的主品牌和兼容品牌识别 AVIF 与 HEIC/HEIF
+fn heif_brand_format(header: &[u8]) -> Option<SourceFormat> {
+    if header.len() < 12 || &header[4..8] != b"ftyp" {
+        return None;
+    }
+
+    let box_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let end = box_size.clamp(12, header.len());
+    // 主品牌在前，其后（跳过 4 字节的次版本号）是兼容品牌列表
+    let brands = std::iter::once(&header[8..12])
+        .chain(header.get(16..end).unwrap_or_default().chunks_exact(4));
+
+    let mut format = None;
+    for brand in brands {
+        match brand {
+            b"avif" | b"avis" => return Some(SourceFormat::Avif),
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => {
+                format = Some(SourceFormat::Heif)
+            }
+            _ => {}
+        }
+    }
+    format
+}
+
+/// 校验图片文件：按内容识别格式，并拒绝不完整或已损坏的文件
+///
+/// 文件结构通过带缓冲的读取逐段检查，不会把整个文件读入内存（RAW 和 HEIF 解码时除外）。
+pub fn validate_image_file(path: &Path) -> Result<SourceFormat, String> {
+    let file = File::open(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let len = file.metadata().map_err(|e| format!("读取文件失败: {}", e))?.len();
+    let mut reader = BufReader::new(file);
+    let header = read_header(&mut reader).map_err(|e| format!("读取文件失败: {}", e))?;
+
+    let format = SourceFormat::from_magic(&header).ok_or_else(|| {
+        "不是支持的图片格式（支持 JPEG、PNG、WebP、GIF、BMP、TIFF、AVIF、HEIC 和 CR2、NEF、ARW、DNG）"
+            .to_string()
+    })?;
+
+    // JPEG 解码器会把截断的数据补齐，PNG 缺少结尾块也能解码，需要单独检查文件结构
+    let complete = match format {
+        SourceFormat::Jpeg => is_complete_jpeg(&mut reader, len),
+        SourceFormat::Png => is_complete_png(&mut reader, len),
+        SourceFormat::WebP => is_complete_riff(&mut reader, len),
+        SourceFormat::Gif => len > 0 && read_array::<_, 1>(&mut reader, len - 1) == Some([0x3B]),
+        SourceFormat::Bmp => is_complete_bmp(&mut reader, len),
+        SourceFormat::Tiff => is_complete_tiff(&mut reader, len),
+        SourceFormat::Avif | SourceFormat::Heif => is_complete_isobmff(&mut reader, len),
+        // RAW 数据本身无法校验，以内嵌预览图是否完整为准
+        SourceFormat::Raw(_) => {
+            let data = read_for_decoding(path, len)?;
+            raw::extract_preview(&data)
+                .is_some_and(|preview| is_complete_jpeg(&mut Cursor::new(preview), preview.len() as u64))
+        }
+    };
+    if !complete {
+        return Err("图片文件不完整".to_string());
+    }
+
+    // 未启用 HEIF 解码时只能检查文件结构
+    if matches!(format, SourceFormat::Avif | SourceFormat::Heif) && !cfg!(feature = "heif") {
+        return Ok(format);
+    }
+
+    open_image(path).map_err(|e| format!("图片已损坏: {}", e))?;
+
+    Ok(format)
+}
+
+/// 读取用于识别格式的文件头
+fn read_header<R: Read + Seek>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    reader.seek(SeekFrom::Start(0))?;
+    reader.by_ref().take(HEADER_SIZE).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// 读取整个文件用于解码（RAW 预览图和 HEIF 的解码器需要完整数据），超过上限时拒绝
+fn read_for_decoding(path: &Path, len: u64) -> Result<Vec<u8>, String> {
+    if len > MAX_DECODE_IN_MEMORY {
+        return Err(format!("文件过大（超过 {} 字节），无法解码", MAX_DECODE_IN_MEMORY));
+    }
+    fs::read(path).map_err(|e| format!("读取文件失败: {}", e))
+}
+
+/// 读取 pos 处的 N 个字节，超出文件末尾时返回 None
+fn read_array<R: Read + Seek, const N: usize>(reader: &mut R, pos: u64) -> Option<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.seek(SeekFrom::Start(pos)).ok()?;
+    reader.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// 逐段遍历 JPEG 标记，直到图像结束标记（EOI）
+fn is_complete_jpeg<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    let mut pos = 2;
+
+    while pos + 1 < len {
+        let Some([0xFF, marker]) = read_array::<_, 2>(reader, pos) else {
+            return false;
+        };
+        pos += 2;
+
+        match marker {
+            // 填充字节
+            0xFF => pos -= 1,
+            0xD9 => return true,
+            // 不带长度的标记
+            0x01 | 0xD0..=0xD7 => {}
+            _ => {
+                let Some(length) = read_array::<_, 2>(reader, pos) else {
+                    return false;
+                };
+                let length = u16::from_be_bytes(length) as u64;
+                if length < 2 {
+                    return false;
+                }
+                pos += length;
+
+                // 扫描数据段（SOS）之后是熵编码数据，跳到下一个非 RST 标记
+                if marker == 0xDA {
+                    match find_jpeg_marker(reader, pos) {
+                        Some(next) => pos = next,
+                        None => return false,
+                    }
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// 从 start 开始顺序查找熵编码数据之后的第一个标记（0xFF 后不是 0x00 或 RST）
+fn find_jpeg_marker<R: Read + Seek>(reader: &mut R, start: u64) -> Option<u64> {
+    reader.seek(SeekFrom::Start(start)).ok()?;
+    let mut buf = [0u8; 8192];
+    let mut pos = start;
+    let mut prev_ff = false;
+
+    loop {
+        let read = reader.read(&mut buf).ok()?;
+        if read == 0 {
+            return None;
+        }
+        for &byte in &buf[..read] {
+            if prev_ff && byte != 0x00 && !(0xD0..=0xD7).contains(&byte) {
+                return Some(pos - 1);
+            }
+            prev_ff = byte == 0xFF;
+            pos += 1;
+        }
+    }
+}
+
+/// 逐块遍历 PNG，直到 IEND 块
+fn is_complete_png<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    let mut pos = 8;
+
+    while pos + 8 <= len {
+        let Some(header) = read_array::<_, 8>(reader, pos) else {
+            return false;
+        };
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        // 长度、类型、数据和 CRC
+        let end = pos + 12 + length;
+        if end > len {
+            return false;
+        }
+        if &header[4..8] == b"IEND" {
+            return true;
+        }
+        pos = end;
+    }
+
+    false
+}
+
+/// BMP 文件头中记录的文件大小（为 0 时不检查）和像素数据偏移不能超过实际文件大小
+fn is_complete_bmp<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    let Some(header) = read_array::<_, 14>(reader, 0) else {
+        return false;
+    };
+    let file_size = u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as u64;
+    let pixel_offset = u32::from_le_bytes([header[10], header[11], header[12], header[13]]) as u64;
+    file_size <= len && pixel_offset < len
+}
+
+/// 逐个遍历 ISOBMFF 顶层盒，最后一个盒必须完整结束于文件末尾
+fn is_complete_isobmff<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    let mut pos: u64 = 0;
+
+    while pos < len {
+        let Some(header) = read_array::<_, 8>(reader, pos) else {
+            return false;
+        };
+        let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let box_size = match size {
+            // 盒延伸到文件末尾
+            0 => return true,
+            // 64 位长度
+            1 => match read_array::<_, 8>(reader, pos + 8) {
+                Some(large) => u64::from_be_bytes(large),
+                None => return false,
+            },
+            _ => size,
+        };
+        if box_size < 8 {
+            return false;
+        }
+        pos = match pos.checked_add(box_size) {
+            Some(next) => next,
+            None => return false,
+        };
+    }
+
+    pos == len
+}
+
+/// RIFF 头中记录的大小不能超过实际文件大小
+fn is_complete_riff<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    len >= 12
+        && read_array::<_, 4>(reader, 4)
+            .is_some_and(|size| u32::from_le_bytes(size) as u64 + 8 <= len)
+}
+
+/// 遍历 TIFF 的 IFD 链，每个 IFD 的条带（StripOffsets/StripByteCounts）或
+/// 图块（TileOffsets/TileByteCounts）都必须完整位于文件内，第一个 IFD 必须包含图像数据
+fn is_complete_tiff<R: Read + Seek>(reader: &mut R, len: u64) -> bool {
+    let Some(header) = read_array::<_, 8>(reader, 0) else {
+        return false;
+    };
+    let big_endian = &header[..2] == b"MM";
+    let read_u16 = |bytes: [u8; 2]| if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) };
+    let read_u32 = |bytes: [u8; 4]| if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) };
+
+    let mut ifd = read_u32([header[4], header[5], header[6], header[7]]) as u64;
+    let mut visited = HashSet::new();
+
+    while ifd != 0 {
+        if visited.len() >= MAX_TIFF_IFDS || !visited.insert(ifd) {
+            return false;
+        }
+        let Some(count) = read_array::<_, 2>(reader, ifd) else {
+            return false;
+        };
+        let count = read_u16(count) as u64;
+        if ifd + 2 + count * 12 + 4 > len {
+            return false;
+        }
+
+        let mut offsets = None;
+        let mut byte_counts = None;
+        for i in 0..count {
+            let Some(entry) = read_array::<_, 12>(reader, ifd + 2 + i * 12) else {
+                return false;
+            };
+            let tag = read_u16([entry[0], entry[1]]);
+            let values = match tag {
+                TIFF_STRIP_OFFSETS | TIFF_TILE_OFFSETS => &mut offsets,
+                TIFF_STRIP_BYTE_COUNTS | TIFF_TILE_BYTE_COUNTS => &mut byte_counts,
+                _ => continue,
+            };
+
+            let field_type = read_u16([entry[2], entry[3]]);
+            let count = read_u32([entry[4], entry[5], entry[6], entry[7]]) as u64;
+            let value = [entry[8], entry[9], entry[10], entry[11]];
+            match read_tiff_values(reader, len, field_type, count, value, read_u16, read_u32) {
+                Some(list) => *values = Some(list),
+                None => return false,
+            }
+        }
+
+        match (offsets, byte_counts) {
+            (Some(offsets), Some(byte_counts)) => {
+                if offsets.len() != byte_counts.len()
+                    || offsets
+                        .iter()
+                        .zip(&byte_counts)
+                        .any(|(offset, size)| offset + size > len)
+                {
+                    return false;
+                }
+            }
+            (None, None) if visited.len() > 1 => {}
+            _ => return false,
+        }
+
+        let Some(next) = read_array::<_, 4>(reader, ifd + 2 + count * 12) else {
+            return false;
+        };
+        ifd = read_u32(next) as u64;
+    }
+
+    !visited.is_empty()
+}
+
+/// 读取 SHORT 或 LONG 类型的 IFD 条目值，总长度不超过 4 字节时值直接存放在条目中
+fn read_tiff_values<R: Read + Seek>(
+    reader: &mut R,
+    len: u64,
+    field_type: u16,
+    count: u64,
+    value: [u8; 4],
+    read_u16: impl Fn([u8; 2]) -> u16,
+    read_u32: impl Fn([u8; 4]) -> u32,
+) -> Option<Vec<u64>> {
+    let size = match field_type {
+        3 => 2,
+        4 => 4,
+        _ => return None,
+    };
+    let total = count * size;
+    if total > len {
+        return None;
+    }
+
+    let bytes = if total <= 4 {
+        value[..total as usize].to_vec()
+    } else {
+        let start = read_u32(value) as u64;
+        if start + total > len {
+            return None;
+        }
+        let mut bytes = vec![0u8; total as usize];
+        reader.seek(SeekFrom::Start(start)).ok()?;
+        reader.read_exact(&mut bytes).ok()?;
+        bytes
+    };
+
+    Some(
+        bytes
+            .chunks_exact(size as usize)
+            .map(|chunk| match chunk {
+                [a, b] => read_u16([*a, *b]) as u64,
+                [a, b, c, d] => read_u32([*a, *b, *c, *d]) as u64,
+                _ => 0,
+            })
+            .collect(),
+    )
+}
+
+/// 解码图片文件，并按 EXIF 方向信息旋转到正确朝向
+///
+/// 常见格式通过带缓冲的读取解码，RAW 和 HEIF 需要读入完整数据。
+pub fn open_image(path: &Path) -> Result<DynamicImage, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开图片: {}", e))?;
+    let len = file.metadata().map_err(|e| format!("无法打开图片: {}", e))?.len();
+    let mut reader = BufReader::new(file);
+    let header = read_header(&mut reader).map_err(|e| format!("无法打开图片: {}", e))?;
+
+    match SourceFormat::from_magic(&header) {
+        Some(SourceFormat::Avif | SourceFormat::Heif | SourceFormat::Raw(_)) => {
+            decode_image(&read_for_decoding(path, len)?)
+        }
+        _ => {
+            reader
+                .seek(SeekFrom::Start(0))
+                .map_err(|e| format!("无法打开图片: {}", e))?;
+            let reader = ImageReader::new(reader)
+                .with_guessed_format()
+                .map_err(|e| format!("无法识别图片格式: {}", e))?;
+            decode_reader(reader)
+        }
+    }
+}
+
+/// 解码内存中的图片数据，并按方向信息旋转到正确朝向
+///
+/// GIF 动图只解码第一帧。
+pub fn decode_image(data: &[u8]) -> Result<DynamicImage, String> {
+    match SourceFormat::from_magic(data) {
+        Some(SourceFormat::Avif | SourceFormat::Heif) => return decode_heif(data),
+        Some(SourceFormat::Raw(_)) => return decode_raw_preview(data),
+        _ => {}
+    }
+
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| format!("无法识别图片格式: {}", e))?;
+    decode_reader(reader)
+}
+
+fn decode_reader<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<DynamicImage, String> {
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("无法解码图片: {}", e))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("无法解码图片: {}", e))?;
+    img.apply_orientation(orientation);
+
+    Ok(img)
+}
+
+/// 解码 RAW 的内嵌 JPEG 预览图，方向以 RAW 文件中的 EXIF 方向为准
+fn decode_raw_preview(data: &[u8]) -> Result<DynamicImage, String> {
+    let preview = raw::extract_preview(data)
+        .ok_or_else(|| "RAW 文件中没有可用的 JPEG 预览图".to_string())?;
+
+    let mut img = ImageReader::with_format(Cursor::new(preview), ImageFormat::Jpeg)
+        .decode()
+        .map_err(|e| format!("无法解码 RAW 预览图: {}", e))?;
+    if let Some(orientation) = raw::orientation(data).and_then(Orientation::from_exif) {
+        img.apply_orientation(orientation);
+    }
+
+    Ok(img)
+}
+
+/// 通过 libheif 解码 HEIC/HEIF 和 AVIF，libheif 会按容器中的旋转、镜像信息转正
+#[cfg(feature = "heif")]
+fn decode_heif(data: &[u8]) -> Result<DynamicImage, String> {
+    use image::{RgbImage, RgbaImage};
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let context = HeifContext::read_from_bytes(data)
+        .map_err(|e| format!("无法解析 HEIF 图片: {}", e))?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|e| format!("无法读取 HEIF 主图: {}", e))?;
+
+    let has_alpha = handle.has_alpha_channel();
+    let chroma = if has_alpha { RgbChroma::Rgba } else { RgbChroma::Rgb };
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(|e| format!("无法解码 HEIF 图片: {}", e))?;
+
+    let planes = decoded.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| "HEIF 解码结果缺少像素数据".to_string())?;
+
+    // 每行末尾可能有对齐填充，逐行拷贝有效像素
+    let row_len = plane.width as usize * if has_alpha { 4 } else { 3 };
+    let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+
+    let img = if has_alpha {
+        RgbaImage::from_raw(plane.width, plane.height, pixels).map(DynamicImage::ImageRgba8)
+    } else {
+        RgbImage::from_raw(plane.width, plane.height, pixels).map(DynamicImage::ImageRgb8)
+    };
+    img.ok_or_else(|| "HEIF 像素数据不完整".to_string())
+}
+
+#[cfg(not(feature = "heif"))]
+fn decode_heif(_data: &[u8]) -> Result<DynamicImage, String> {
+    Err("当前版本未启用 HEIC/AVIF 解码（编译时需开启 heif 功能）".to_string())
+}
+
+/// 图片输出编码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl OutputFormat {
+    /// 文件扩展名（含点号）
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => ".jpg",
+            OutputFormat::Png => ".png",
+            OutputFormat::WebP => ".webp",
+        }
+    }
+}
+
+/// 将图片编码为指定格式，quality 仅对 JPEG 生效（WebP 编码器只支持无损）
+pub fn encode_image(img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>, String> {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::codecs::png::PngEncoder;
+    use image::codecs::webp::WebPEncoder;
+    use std::io::Cursor;
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let result = match format {
+        OutputFormat::Jpeg => {
+            // JPEG 不支持透明通道和 16 位色深
+            let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, 100)))
+        }
+        OutputFormat::Png => img.write_with_encoder(PngEncoder::new(&mut buffer)),
+        OutputFormat::WebP => {
+            let converted = if img.color().has_alpha() {
+                DynamicImage::ImageRgba8(img.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(img.to_rgb8())
+            };
+            converted.write_with_encoder(WebPEncoder::new_lossless(&mut buffer))
+        }
+    };
+
+    result.map_err(|e| format!("图片编码失败: {}", e))?;
+    Ok(buffer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 小端 TIFF：IFD0 位于偏移 8，包含一个条带，条带数据位于 strip_offset
+    fn tiff_with_strip(strip_offset: u32, strip_size: u32, next_ifd: u32) -> Vec<u8> {
+        let mut data = b"II*\0".to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        for (tag, value) in [(TIFF_STRIP_OFFSETS, strip_offset), (TIFF_STRIP_BYTE_COUNTS, strip_size)] {
+            data.extend(tag.to_le_bytes());
+            data.extend(4u16.to_le_bytes());
+            data.extend(1u32.to_le_bytes());
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(next_ifd.to_le_bytes());
+        data
+    }
+
+    fn check_tiff(data: &[u8]) -> bool {
+        is_complete_tiff(&mut Cursor::new(data), data.len() as u64)
+    }
+
+    #[test]
+    fn complete_tiff_passes() {
+        let mut data = tiff_with_strip(38, 16, 0);
+        data.extend([0u8; 16]);
+        assert!(check_tiff(&data));
+    }
+
+    #[test]
+    fn truncated_tiff_strip_is_rejected() {
+        let mut data = tiff_with_strip(38, 16, 0);
+        data.extend([0u8; 10]);
+        assert!(!check_tiff(&data));
+        assert!(!check_tiff(&tiff_with_strip(u32::MAX, 16, 0)));
+    }
+
+    #[test]
+    fn broken_tiff_ifd_chain_is_rejected() {
+        // 下一个 IFD 超出文件末尾、指向自身，或 IFD 本身被截断
+        let mut data = tiff_with_strip(38, 2, 1000);
+        data.extend([0u8; 2]);
+        assert!(!check_tiff(&data));
+
+        let mut data = tiff_with_strip(38, 2, 8);
+        data.extend([0u8; 2]);
+        assert!(!check_tiff(&data));
+
+        assert!(!check_tiff(&tiff_with_strip(38, 2, 0)[..20]));
+    }
+
+    #[test]
+    fn truncated_jpeg_is_rejected() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0x00, 0x56, 0xFF, 0xD9];
+        assert!(is_complete_jpeg(&mut Cursor::new(&jpeg[..]), jpeg.len() as u64));
+        let truncated = &jpeg[..10];
+        assert!(!is_complete_jpeg(&mut Cursor::new(truncated), truncated.len() as u64));
+    }
+}
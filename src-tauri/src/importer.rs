@@ -0,0 +1,245 @@
+use crate::image::validate_image_format;
+use crate::upload;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use walkdir::WalkDir;
+
+/// 导入进度事件名
+pub const PROGRESS_EVENT: &str = "import-progress";
+
+/// 最多同时导入的文件数
+const MAX_IMPORT_WORKERS: usize = 8;
+
+/// 同一时间只允许一个批量导入任务
+static IMPORT_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 取消标记，导入任务在处理每个文件前检查
+static IMPORT_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// 导入进度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportProgress {
+    /// 已处理的文件数
+    pub processed: usize,
+    /// 待导入的文件总数
+    pub total: usize,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// 最近处理完成的文件
+    pub current_file: String,
+    pub bytes_processed: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedFile {
+    pub path: String,
+    pub image_id: i32,
+}
+
+/// 跳过或失败的文件及原因
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportIssue {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// 待导入的图片文件数量
+    pub total: usize,
+    pub imported: Vec<ImportedFile>,
+    /// 重复而跳过的文件
+    pub skipped: Vec<ImportIssue>,
+    pub failed: Vec<ImportIssue>,
+    /// 不是支持的图片格式而忽略的文件数量
+    pub ignored: usize,
+    /// 是否被取消（取消时只包含已处理的文件）
+    pub cancelled: bool,
+}
+
+enum ImportOutcome {
+    Imported(i32),
+    Skipped(String),
+    Failed(String),
+}
+
+/// 导入结束时清除运行标记
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        IMPORT_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 取消正在进行的批量导入，返回是否有导入任务在运行
+pub fn cancel_import() -> bool {
+    let running = IMPORT_RUNNING.load(Ordering::SeqCst);
+    if running {
+        IMPORT_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    running
+}
+
+/// 收集目录下的图片文件及其大小，不跟随符号链接
+fn collect_image_files(dir: &Path, recursive: bool, report: &mut ImportReport) -> Vec<(PathBuf, u64)> {
+    let mut walker = WalkDir::new(dir).sort_by_file_name();
+    if !recursive {
+        walker = walker.max_depth(1);
+    }
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.failed.push(ImportIssue {
+                    path: e.path().map(|p| p.display().to_string()).unwrap_or_default(),
+                    reason: format!("读取目录失败: {}", e),
+                });
+                continue;
+            }
+        };
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        if !matches!(validate_image_format(&path.to_string_lossy()), Ok(true)) {
+            report.ignored += 1;
+            continue;
+        }
+
+        let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        files.push((path.to_path_buf(), size));
+    }
+
+    files
+}
+
+/// 导入单个文件；本次导入中内容相同的文件只导入第一个
+fn import_file(path: &Path, claimed: &Mutex<HashMap<String, PathBuf>>) -> ImportOutcome {
+    let filename = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return ImportOutcome::Failed("无法获取文件名".to_string()),
+    };
+
+    let hash = match upload::calculate_file_hash(path) {
+        Ok(hash) => hash,
+        Err(e) => return ImportOutcome::Failed(format!("计算文件哈希失败: {}", e)),
+    };
+
+    {
+        let mut claimed = claimed.lock().unwrap();
+        if let Some(first) = claimed.get(&hash) {
+            return ImportOutcome::Skipped(format!("与 {} 内容相同", first.display()));
+        }
+        claimed.insert(hash.clone(), path.to_path_buf());
+    }
+
+    match upload::import_hashed_image_file(path, &filename, &hash) {
+        Ok(result) => match result.image_id {
+            Some(image_id) => ImportOutcome::Imported(image_id),
+            None => ImportOutcome::Skipped("图库中已存在相同的图片".to_string()),
+        },
+        Err(e) => ImportOutcome::Failed(e),
+    }
+}
+
+/// 批量导入目录中的图片
+///
+/// 多个线程并行计算哈希并导入，每处理完一个文件调用一次 on_progress。
+pub fn import_directory<F>(dir: &Path, recursive: bool, on_progress: F) -> Result<ImportReport, String>
+where
+    F: Fn(&ImportProgress) + Sync,
+{
+    if !dir.is_dir() {
+        return Err(format!("目录不存在: {}", dir.display()));
+    }
+
+    if IMPORT_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("已有导入任务正在进行".to_string());
+    }
+    let _guard = RunningGuard;
+    IMPORT_CANCELLED.store(false, Ordering::SeqCst);
+
+    let mut report = ImportReport::default();
+    let files = collect_image_files(dir, recursive, &mut report);
+    report.total = files.len();
+
+    let progress = ImportProgress {
+        total: files.len(),
+        bytes_total: files.iter().map(|(_, size)| size).sum(),
+        ..Default::default()
+    };
+    on_progress(&progress);
+
+    let state = Mutex::new((progress, report));
+    let claimed = Mutex::new(HashMap::new());
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_IMPORT_WORKERS);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if IMPORT_CANCELLED.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some((path, size)) = files.get(index) else {
+                    break;
+                };
+
+                let outcome = import_file(path, &claimed);
+                let display_path = path.display().to_string();
+
+                let mut state = state.lock().unwrap();
+                let (progress, report) = &mut *state;
+                match outcome {
+                    ImportOutcome::Imported(image_id) => {
+                        progress.imported += 1;
+                        report.imported.push(ImportedFile {
+                            path: display_path.clone(),
+                            image_id,
+                        });
+                    }
+                    ImportOutcome::Skipped(reason) => {
+                        progress.skipped += 1;
+                        report.skipped.push(ImportIssue {
+                            path: display_path.clone(),
+                            reason,
+                        });
+                    }
+                    ImportOutcome::Failed(reason) => {
+                        progress.failed += 1;
+                        report.failed.push(ImportIssue {
+                            path: display_path.clone(),
+                            reason,
+                        });
+                    }
+                }
+                progress.processed += 1;
+                progress.bytes_processed += size;
+                progress.current_file = display_path;
+                on_progress(progress);
+            });
+        }
+    });
+
+    let (progress, mut report) = state.into_inner().unwrap();
+    report.cancelled =
+        IMPORT_CANCELLED.swap(false, Ordering::SeqCst) && progress.processed < progress.total;
+
+    Ok(report)
+}
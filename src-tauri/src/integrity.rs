@@ -0,0 +1,398 @@
+use crate::config;
+use crate::database::{self, ImageRecord};
+use crate::image::validate_image_format;
+use crate::rendition;
+use crate::thumbnail;
+use crate::trash;
+use crate::upload::{calculate_file_hash, get_upload_temp_dir, import_image_file};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 问题类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// 数据库记录指向的文件不存在
+    MissingFile,
+    /// 存储目录中的文件没有对应的数据库记录
+    OrphanFile,
+    /// 记录的缩略图文件不存在
+    MissingThumbnail,
+    /// 文件内容的 SHA-256 与记录的哈希不一致
+    HashMismatch,
+    /// 文件存在但无法读取（权限或 I/O 错误），不做任何修复
+    UnreadableFile,
+    /// 文件已被移入隔离目录，等待从备份恢复，不做任何修复
+    Quarantined,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub image_id: Option<i32>,
+    pub path: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// 检查的数据库记录数量
+    pub checked_images: usize,
+    /// 扫描的存储目录文件数量
+    pub scanned_files: usize,
+    /// 是否校验了文件哈希
+    pub hashes_checked: bool,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+/// 修复操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairAction {
+    /// 重新导入孤立文件
+    ReimportOrphans,
+    /// 删除文件不存在的记录
+    DropDanglingRows,
+    /// 重新生成缺失的缩略图
+    RegenerateThumbnails,
+    /// 将哈希不一致的文件移到隔离目录
+    QuarantineCorrupted,
+}
+
+impl RepairAction {
+    fn handles(self, kind: IssueKind) -> bool {
+        matches!(
+            (self, kind),
+            (RepairAction::ReimportOrphans, IssueKind::OrphanFile)
+                | (RepairAction::DropDanglingRows, IssueKind::MissingFile)
+                | (RepairAction::RegenerateThumbnails, IssueKind::MissingThumbnail)
+                | (RepairAction::QuarantineCorrupted, IssueKind::HashMismatch)
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepairFailure {
+    pub kind: IssueKind,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RepairReport {
+    pub reimported: usize,
+    pub dropped: usize,
+    pub thumbnails: usize,
+    pub quarantined: usize,
+    pub failed: Vec<RepairFailure>,
+}
+
+/// 获取隔离目录，存放哈希校验失败的文件
+pub fn get_quarantine_dir() -> PathBuf {
+    let mut path = config::get_images_dir();
+    path.push(".quarantine");
+    path
+}
+
+/// 所有图片记录，包括回收站中的
+fn all_records() -> Result<Vec<ImageRecord>, String> {
+    let mut records = database::get_all_images()
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+    records.extend(trash::list_trash()?);
+    Ok(records)
+}
+
+/// 存储目录中的图片文件，跳过缩略图、缩放图缓存、上传临时文件和隔离目录
+fn collect_stored_files() -> Vec<PathBuf> {
+    let images_dir = config::get_images_dir();
+    let skipped = [
+        config::get_thumbnails_dir(),
+        config::get_renditions_dir(),
+        get_upload_temp_dir(),
+        get_quarantine_dir(),
+    ];
+
+    WalkDir::new(&images_dir)
+        .into_iter()
+        .filter_entry(|entry| !skipped.iter().any(|dir| entry.path() == dir))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| matches!(validate_image_format(&path.to_string_lossy()), Ok(true)))
+        .collect()
+}
+
+/// 检查图片库的完整性：记录与文件、缩略图是否一致，check_hashes 时重新计算文件哈希
+pub fn verify_library(check_hashes: bool) -> Result<IntegrityReport, String> {
+    let records = all_records()?;
+    let quarantine_dir = get_quarantine_dir();
+    let mut issues = Vec::new();
+
+    for record in &records {
+        let path = Path::new(&record.path);
+        if path.starts_with(&quarantine_dir) {
+            issues.push(IntegrityIssue {
+                kind: IssueKind::Quarantined,
+                image_id: Some(record.id),
+                path: record.path.clone(),
+                detail: None,
+            });
+            continue;
+        }
+
+        if !path.exists() {
+            issues.push(IntegrityIssue {
+                kind: IssueKind::MissingFile,
+                image_id: Some(record.id),
+                path: record.path.clone(),
+                detail: None,
+            });
+            continue;
+        }
+
+        if let Some(thumbnail_path) = &record.thumbnail_path {
+            if !Path::new(thumbnail_path).exists() {
+                issues.push(IntegrityIssue {
+                    kind: IssueKind::MissingThumbnail,
+                    image_id: Some(record.id),
+                    path: thumbnail_path.clone(),
+                    detail: None,
+                });
+            }
+        }
+
+        if check_hashes {
+            match calculate_file_hash(path) {
+                Ok(hash) if hash == record.hash => {}
+                Ok(hash) => issues.push(IntegrityIssue {
+                    kind: IssueKind::HashMismatch,
+                    image_id: Some(record.id),
+                    path: record.path.clone(),
+                    detail: Some(format!("期望 {}，实际 {}", record.hash, hash)),
+                }),
+                Err(e) => issues.push(IntegrityIssue {
+                    kind: IssueKind::UnreadableFile,
+                    image_id: Some(record.id),
+                    path: record.path.clone(),
+                    detail: Some(e),
+                }),
+            }
+        }
+    }
+
+    let known: HashSet<PathBuf> = records.iter().map(|r| PathBuf::from(&r.path)).collect();
+    let files = collect_stored_files();
+    for file in &files {
+        if !known.contains(file) {
+            issues.push(IntegrityIssue {
+                kind: IssueKind::OrphanFile,
+                image_id: None,
+                path: file.to_string_lossy().to_string(),
+                detail: None,
+            });
+        }
+    }
+
+    Ok(IntegrityReport {
+        checked_images: records.len(),
+        scanned_files: files.len(),
+        hashes_checked: check_hashes,
+        issues,
+    })
+}
+
+/// 重新导入孤立文件：先移到上传临时目录，避免复制到存储路径时覆盖自身
+fn reimport_orphan(path: &Path) -> Result<(), String> {
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "无法获取文件名".to_string())?;
+
+    let staging_dir = get_upload_temp_dir();
+    fs::create_dir_all(&staging_dir).map_err(|e| format!("创建临时目录失败: {}", e))?;
+    let staging = staging_dir.join(format!("reimport-{}", filename));
+    fs::rename(path, &staging).map_err(|e| format!("移动文件失败: {}", e))?;
+
+    match import_image_file(&staging, &filename) {
+        Ok(_) => {
+            // 导入成功或内容已在库中，临时文件都不再需要
+            let _ = fs::remove_file(&staging);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::rename(&staging, path);
+            Err(e)
+        }
+    }
+}
+
+/// 删除文件已不存在的记录，连同缩略图和缓存的缩放图
+fn drop_dangling_row(image_id: i32) -> Result<(), String> {
+    let record = database::get_image_by_id(image_id)
+        .map_err(|e| format!("图片不存在: {}", e))?;
+    if Path::new(&record.path).exists() {
+        return Err("文件已存在，不再删除记录".to_string());
+    }
+
+    database::delete_image(image_id).map_err(|e| format!("删除数据库记录失败: {}", e))?;
+    if let Some(thumbnail_path) = &record.thumbnail_path {
+        let _ = fs::remove_file(thumbnail_path);
+    }
+    rendition::remove_renditions(&record.hash);
+    Ok(())
+}
+
+fn regenerate_thumbnail(image_id: i32) -> Result<(), String> {
+    let record = database::get_image_by_id(image_id)
+        .map_err(|e| format!("图片不存在: {}", e))?;
+    thumbnail::create_thumbnail(record.id, Path::new(&record.path), &record.hash).map(|_| ())
+}
+
+/// 将损坏的文件移到隔离目录，记录保留并指向隔离后的文件（之后可从备份恢复）
+///
+/// 记录指向隔离目录后，检查时报告为 Quarantined，不会被当作缺失文件删除。
+fn quarantine_file(image_id: i32, path: &Path) -> Result<(), String> {
+    let filename = path.file_name().ok_or_else(|| "无法获取文件名".to_string())?;
+    let dir = get_quarantine_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("创建隔离目录失败: {}", e))?;
+
+    let target = dir.join(filename);
+    if target.exists() {
+        return Err(format!("隔离目录中已有同名文件: {}", target.display()));
+    }
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target).map_err(|e| format!("移动文件失败: {}", e))?;
+        fs::remove_file(path).map_err(|e| {
+            let _ = fs::remove_file(&target);
+            format!("删除原文件失败: {}", e)
+        })?;
+    }
+
+    if let Err(e) = database::update_image_path(image_id, &target.to_string_lossy()) {
+        let _ = fs::rename(&target, path);
+        return Err(format!("更新数据库记录失败: {}", e));
+    }
+    Ok(())
+}
+
+/// 重新检查图片库并执行指定的修复操作
+pub fn repair_library(actions: &[RepairAction]) -> Result<RepairReport, String> {
+    let check_hashes = actions.contains(&RepairAction::QuarantineCorrupted);
+    let report = verify_library(check_hashes)?;
+
+    let mut result = RepairReport::default();
+    for issue in report.issues {
+        if !actions.iter().any(|action| action.handles(issue.kind)) {
+            continue;
+        }
+
+        let path = Path::new(&issue.path);
+        let outcome = match (issue.kind, issue.image_id) {
+            (IssueKind::OrphanFile, _) => reimport_orphan(path).map(|_| result.reimported += 1),
+            (IssueKind::MissingFile, Some(id)) => drop_dangling_row(id).map(|_| result.dropped += 1),
+            (IssueKind::MissingThumbnail, Some(id)) => {
+                regenerate_thumbnail(id).map(|_| result.thumbnails += 1)
+            }
+            (IssueKind::HashMismatch, Some(id)) => {
+                quarantine_file(id, path).map(|_| result.quarantined += 1)
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(error) = outcome {
+            result.failed.push(RepairFailure {
+                kind: issue.kind,
+                path: issue.path,
+                error,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    fn issue_kinds(report: &IntegrityReport) -> Vec<IssueKind> {
+        report.issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn orphan_files_are_reimported() {
+        let library = TestLibrary::new();
+        library.import_png("a.png", [200, 30, 30]);
+
+        let orphan_dir = config::get_images_dir().join("ff");
+        fs::create_dir_all(&orphan_dir).unwrap();
+        fs::copy(library.write_png("b.png", [30, 200, 30]), orphan_dir.join("orphan.png")).unwrap();
+        assert_eq!(issue_kinds(&verify_library(false).unwrap()), [IssueKind::OrphanFile]);
+
+        let report = repair_library(&[RepairAction::ReimportOrphans]).unwrap();
+        assert_eq!(report.reimported, 1);
+        assert!(report.failed.is_empty());
+
+        let after = verify_library(true).unwrap();
+        assert!(after.issues.is_empty());
+        assert_eq!(after.checked_images, 2);
+        assert!(!orphan_dir.join("orphan.png").exists());
+    }
+
+    #[test]
+    fn rows_without_files_are_dropped() {
+        let library = TestLibrary::new();
+        let kept = library.import_png("kept.png", [10, 10, 10]);
+        let id = library.import_png("a.png", [30, 30, 200]);
+        let record = database::get_image_by_id(id).unwrap();
+        fs::remove_file(&record.path).unwrap();
+
+        let report = verify_library(false).unwrap();
+        assert_eq!(issue_kinds(&report), [IssueKind::MissingFile]);
+        assert_eq!(report.issues[0].image_id, Some(id));
+
+        let report = repair_library(&[RepairAction::DropDanglingRows]).unwrap();
+        assert_eq!(report.dropped, 1);
+        assert!(database::get_image_by_id(id).is_err());
+        assert!(database::get_image_by_id(kept).is_ok());
+        if let Some(thumbnail_path) = record.thumbnail_path {
+            assert!(!Path::new(&thumbnail_path).exists());
+        }
+        assert!(verify_library(false).unwrap().issues.is_empty());
+    }
+
+    #[test]
+    fn corrupted_files_are_quarantined_and_keep_their_rows() {
+        let library = TestLibrary::new();
+        let id = library.import_png("a.png", [200, 200, 30]);
+        let stored = database::get_image_by_id(id).unwrap().path;
+        fs::write(&stored, b"not the original bytes").unwrap();
+
+        // 不校验哈希时发现不了内容变化
+        assert!(verify_library(false).unwrap().issues.is_empty());
+        assert_eq!(issue_kinds(&verify_library(true).unwrap()), [IssueKind::HashMismatch]);
+
+        let report = repair_library(&[
+            RepairAction::QuarantineCorrupted,
+            RepairAction::DropDanglingRows,
+        ])
+        .unwrap();
+        assert_eq!(report.quarantined, 1);
+        assert_eq!(report.dropped, 0);
+
+        let record = database::get_image_by_id(id).unwrap();
+        assert!(Path::new(&record.path).starts_with(get_quarantine_dir()));
+        assert!(Path::new(&record.path).exists());
+        assert!(!Path::new(&stored).exists());
+
+        // 隔离后的记录不会被当作缺失文件删除
+        assert_eq!(issue_kinds(&verify_library(true).unwrap()), [IssueKind::Quarantined]);
+        let report = repair_library(&[RepairAction::DropDanglingRows]).unwrap();
+        assert_eq!(report.dropped, 0);
+        assert!(database::get_image_by_id(id).is_ok());
+    }
+}
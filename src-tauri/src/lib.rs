@@ -2,5 +2,21 @@ pub mod commands;
 pub mod config;
 pub mod database;
 pub mod image;
+pub mod metadata;
 pub mod upload;
 pub mod api_server;
+pub mod rendition;
+pub mod search;
+pub mod thumbnail;
+pub mod auth;
+pub mod pairing;
+pub mod resumable;
+pub mod importer;
+pub mod watcher;
+pub mod raw;
+pub mod trash;
+pub mod integrity;
+pub mod scrub;
+pub mod backup;
+#[cfg(test)]
+mod test_support;
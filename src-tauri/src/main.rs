@@ -1,11 +1,27 @@
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 mod commands;
 mod config;
 mod database;
 mod image;
+mod metadata;
 mod upload;
 mod api_server;
+mod rendition;
+mod search;
+mod thumbnail;
+mod auth;
+mod pairing;
+mod resumable;
+mod importer;
+mod watcher;
+mod raw;
+mod trash;
+mod integrity;
+mod scrub;
+mod backup;
+#[cfg(test)]
+mod test_support;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 fn main() {
@@ -18,18 +34,51 @@ fn main() {
             commands::get_system_info,
             commands::validate_image,
             commands::login,
+            commands::change_password,
+            commands::get_network_info,
+            commands::create_pairing_code,
             commands::get_all_images,
             commands::search_images,
+            commands::list_images,
             commands::upload_image,
+            commands::import_directory,
+            commands::cancel_import,
+            commands::generate_missing_thumbnails,
+            commands::rescan_metadata,
+            commands::backfill_dimensions,
             commands::get_config,
             commands::update_config,
+            commands::add_watched_folder,
+            commands::remove_watched_folder,
+            commands::rebuild_search_index,
             commands::get_images_directory,
             commands::get_thumbnails_directory,
             commands::get_config_file_path,
             commands::get_image_data,
+            commands::get_image_rendition,
             commands::update_image_info,
             commands::get_image_by_id,
             commands::delete_image,
+            commands::list_trash,
+            commands::restore_images,
+            commands::empty_trash,
+            commands::verify_library,
+            commands::repair_library,
+            commands::scrub_library,
+            commands::get_verification_history,
+            commands::backup_library,
+            commands::restore_library,
+            commands::run_incremental_backup,
+            commands::list_backup_generations,
+            commands::verify_incremental_backup,
+            commands::get_image_tags,
+            commands::add_image_tags,
+            commands::remove_image_tags,
+            commands::bulk_tag_images,
+            commands::bulk_untag_images,
+            commands::rename_tag,
+            commands::list_tags,
+            commands::get_images_by_tag,
             commands::start_server,
             commands::stop_server,
             commands::get_server_status,
@@ -55,6 +104,41 @@ fn main() {
                 eprintln!("数据库初始化失败: {}", e);
             }
 
+            // 首次运行时生成默认密码哈希和令牌签名密钥
+            if let Err(e) = auth::init_credentials() {
+                eprintln!("初始化登录凭据失败: {}", e);
+            }
+
+            // 将旧版本重命名过的文件移回按哈希命名的存储路径
+            match database::migrate_renamed_files() {
+                Ok(count) if count > 0 => println!("已迁移 {} 个重命名文件", count),
+                Ok(_) => {}
+                Err(e) => eprintln!("迁移重命名文件失败: {}", e),
+            }
+
+            // 后台为旧数据补充图片尺寸
+            std::thread::spawn(|| match metadata::backfill_dimensions() {
+                Ok(report) if report.total > 0 => {
+                    println!("补充图片尺寸: {}/{}", report.updated, report.total);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("补充图片尺寸失败: {}", e),
+            });
+
+            // 定期永久删除回收站中超过保留天数的图片
+            trash::start_auto_purge();
+
+            // 每天按额度重新校验一部分图片的哈希，发现文件损坏时通知前端
+            let app_handle = app.handle().clone();
+            scrub::start_scheduler(move |failure| {
+                let _ = app_handle.emit(scrub::MISMATCH_EVENT, failure);
+            });
+
+            // 启动文件夹监视（后台补扫监视文件夹）
+            if let Err(e) = watcher::restart() {
+                eprintln!("启动文件夹监视失败: {}", e);
+            }
+
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,280 @@
+use crate::database::{self, ImageMetadata};
+use crate::raw;
+use exif::{DateTime, Exif, In, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataScanFailure {
+    pub image_id: i32,
+    pub filename: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataScanReport {
+    /// 扫描的图片数量
+    pub total: usize,
+    /// 写入了 EXIF 信息的数量
+    pub updated: usize,
+    /// 没有 EXIF 信息的数量
+    pub without_exif: usize,
+    pub failed: Vec<MetadataScanFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DimensionScanReport {
+    /// 缺少尺寸信息的图片数量
+    pub total: usize,
+    pub updated: usize,
+    pub failed: Vec<MetadataScanFailure>,
+}
+
+/// 读取图片的显示尺寸（宽, 高），只解析文件头
+///
+/// EXIF 方向为旋转 90° 的图片（5~8）交换宽高，与缩略图和转换后的朝向一致。
+pub fn read_dimensions(path: &Path) -> Result<(u32, u32), String> {
+    // RAW 的 IFD0 往往只是小缩略图，需要在完整文件中查找主图像尺寸
+    let raw_size = if matches!(imagesize::image_type(&read_header(path)?), Ok(imagesize::ImageType::Tiff)) {
+        let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+        match raw::detect(&data) {
+            Some(_) => Some(raw::dimensions(&data).ok_or_else(|| "无法读取 RAW 图片尺寸".to_string())?),
+            None => None,
+        }
+    } else {
+        None
+    };
+
+    let (width, height) = match raw_size {
+        Some(size) => size,
+        None => {
+            let size = imagesize::size(path).map_err(|e| format!("读取图片尺寸失败: {}", e))?;
+            (size.width as u32, size.height as u32)
+        }
+    };
+    if width == 0 || height == 0 {
+        return Err("图片尺寸无效".to_string());
+    }
+
+    let rotated = fs::File::open(path)
+        .ok()
+        .and_then(|file| Reader::new().read_from_container(&mut BufReader::new(file)).ok())
+        .and_then(|exif| get_uint(&exif, Tag::Orientation))
+        .is_some_and(|orientation| (5..=8).contains(&orientation));
+
+    Ok(if rotated { (height, width) } else { (width, height) })
+}
+
+fn read_header(path: &Path) -> Result<Vec<u8>, String> {
+    let mut header = Vec::with_capacity(16);
+    fs::File::open(path)
+        .and_then(|file| file.take(16).read_to_end(&mut header))
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    Ok(header)
+}
+
+/// 读取图片尺寸并写入数据库
+pub fn store_dimensions(image_id: i32, path: &Path) -> Result<(), String> {
+    let (width, height) = read_dimensions(path)?;
+    database::update_image_dimensions(image_id, width, height)
+        .map_err(|e| format!("保存图片尺寸失败: {}", e))
+}
+
+/// 为缺少尺寸信息的图片（旧版本导入的图片）补充宽高
+pub fn backfill_dimensions() -> Result<DimensionScanReport, String> {
+    let records = database::get_images_without_dimensions()
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+
+    let mut report = DimensionScanReport {
+        total: records.len(),
+        updated: 0,
+        failed: Vec::new(),
+    };
+
+    for record in records {
+        match store_dimensions(record.id, Path::new(&record.path)) {
+            Ok(()) => report.updated += 1,
+            Err(error) => report.failed.push(MetadataScanFailure {
+                image_id: record.id,
+                filename: record.filename,
+                error,
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+/// 读取图片文件中的 EXIF 信息，文件不含 EXIF 时返回 None
+pub fn read_exif_metadata(path: &Path) -> Result<Option<ImageMetadata>, String> {
+    let file = fs::File::open(path)
+        .map_err(|e| format!("无法打开文件: {}", e))?;
+
+    let exif = match Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(exif::Error::NotFound(_)) => return Ok(None),
+        Err(exif::Error::InvalidFormat(_)) => return Ok(None),
+        Err(e) => return Err(format!("解析 EXIF 失败: {}", e)),
+    };
+
+    let metadata = ImageMetadata {
+        exif_make: get_ascii(&exif, Tag::Make),
+        exif_model: get_ascii(&exif, Tag::Model),
+        exif_iso: get_uint(&exif, Tag::PhotographicSensitivity).map(i64::from),
+        exif_aperture: get_aperture(&exif),
+        exif_exposure_time: get_exposure_time(&exif),
+        exif_datetime: get_datetime(&exif, Tag::DateTimeOriginal)
+            .or_else(|| get_datetime(&exif, Tag::DateTime)),
+        gps_latitude: get_gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S'),
+        gps_longitude: get_gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W'),
+        gps_altitude: get_gps_altitude(&exif),
+        description: get_ascii(&exif, Tag::ImageDescription),
+    };
+
+    Ok(Some(metadata))
+}
+
+/// 读取 EXIF 并写入数据库，返回是否存在 EXIF 信息
+pub fn extract_and_store(image_id: i32, path: &Path) -> Result<bool, String> {
+    match read_exif_metadata(path)? {
+        Some(metadata) => {
+            database::upsert_image_metadata(image_id, &metadata)
+                .map_err(|e| format!("保存 EXIF 信息失败: {}", e))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// 重新扫描所有图片的 EXIF 信息
+pub fn rescan_all_metadata() -> Result<MetadataScanReport, String> {
+    let records = database::get_all_images()
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+
+    let mut report = MetadataScanReport {
+        total: records.len(),
+        updated: 0,
+        without_exif: 0,
+        failed: Vec::new(),
+    };
+
+    for record in records {
+        match extract_and_store(record.id, Path::new(&record.path)) {
+            Ok(true) => report.updated += 1,
+            Ok(false) => report.without_exif += 1,
+            Err(error) => report.failed.push(MetadataScanFailure {
+                image_id: record.id,
+                filename: record.filename,
+                error,
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn get_field(exif: &Exif, tag: Tag) -> Option<&Value> {
+    exif.get_field(tag, In::PRIMARY).map(|field| &field.value)
+}
+
+fn get_ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match get_field(exif, tag)? {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?).trim().to_string();
+            if text.is_empty() {
+                None
+            } else {
+                Some(text)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn get_uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    get_field(exif, tag)?.get_uint(0)
+}
+
+fn get_rational(exif: &Exif, tag: Tag) -> Option<f64> {
+    match get_field(exif, tag)? {
+        Value::Rational(values) => {
+            let r = values.first()?;
+            if r.denom == 0 {
+                None
+            } else {
+                Some(r.to_f64())
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 光圈：优先使用 FNumber，没有时由 APEX 光圈值换算
+fn get_aperture(exif: &Exif) -> Option<f64> {
+    let value = get_rational(exif, Tag::FNumber)
+        .or_else(|| get_rational(exif, Tag::ApertureValue).map(|apex| 2f64.powf(apex / 2.0)))?;
+
+    // 保留一位小数，如 f/2.8
+    Some((value * 10.0).round() / 10.0)
+}
+
+/// 曝光时间：小于 1 秒时表示为 "1/N"，否则为秒数
+fn get_exposure_time(exif: &Exif) -> Option<String> {
+    let seconds = get_rational(exif, Tag::ExposureTime)?;
+    if seconds <= 0.0 {
+        return None;
+    }
+
+    if seconds < 1.0 {
+        Some(format!("1/{}", (1.0 / seconds).round()))
+    } else if seconds.fract() == 0.0 {
+        Some(format!("{}", seconds))
+    } else {
+        Some(format!("{:.1}", seconds))
+    }
+}
+
+fn get_datetime(exif: &Exif, tag: Tag) -> Option<String> {
+    match get_field(exif, tag)? {
+        Value::Ascii(values) => {
+            let dt = DateTime::from_ascii(values.first()?).ok()?;
+            Some(format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// GPS 经纬度：度分秒转换为十进制，南纬/西经为负值
+fn get_gps_coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: u8) -> Option<f64> {
+    let dms = match exif.get_field(tag, In::PRIMARY).map(|field| &field.value)? {
+        Value::Rational(values) if values.len() >= 3 && values.iter().all(|r| r.denom != 0) => values,
+        _ => return None,
+    };
+
+    let mut value = dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0;
+
+    if let Some(Value::Ascii(refs)) = exif.get_field(ref_tag, In::PRIMARY).map(|field| &field.value) {
+        if refs.first().and_then(|r| r.first()) == Some(&negative_ref) {
+            value = -value;
+        }
+    }
+
+    Some(value)
+}
+
+/// GPS 海拔：参考值为 1 时表示海平面以下
+fn get_gps_altitude(exif: &Exif) -> Option<f64> {
+    let altitude = get_rational(exif, Tag::GPSAltitude)?;
+
+    let below_sea_level = matches!(
+        exif.get_field(Tag::GPSAltitudeRef, In::PRIMARY).map(|field| &field.value),
+        Some(Value::Byte(refs)) if refs.first() == Some(&1)
+    );
+
+    Some(if below_sea_level { -altitude } else { altitude })
+}
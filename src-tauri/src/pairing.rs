@@ -0,0 +1,119 @@
+use crate::api_server;
+use crate::auth::{self, LoginResponse};
+use crate::image::{encode_image, OutputFormat};
+use base64::{engine::general_purpose, Engine as _};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 配对令牌有效期（秒）
+const PAIRING_TOKEN_TTL_SECS: u64 = 300;
+
+/// 二维码最小边长（像素）
+const QR_CODE_SIZE: u32 = 256;
+
+/// 未使用的配对令牌及其过期时间，只保存在内存中，应用重启后全部失效
+static PAIRING_TOKENS: Mutex<Option<HashMap<String, u64>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PairingCode {
+    /// 服务器地址
+    pub server_url: String,
+    /// 一次性配对令牌
+    pub token: String,
+    pub expires_at: u64,
+    /// 二维码中编码的地址：服务器地址加配对令牌
+    pub pairing_url: String,
+    /// 二维码图片（data URL）
+    pub qr_code: String,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 生成一次性配对令牌
+fn mint_pairing_token() -> (String, u64) {
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let now = now_secs();
+    let expires_at = now + PAIRING_TOKEN_TTL_SECS;
+
+    let mut tokens = PAIRING_TOKENS.lock().unwrap();
+    let tokens = tokens.get_or_insert_with(HashMap::new);
+    // 顺便清理已过期的令牌
+    tokens.retain(|_, exp| *exp > now);
+    tokens.insert(token.clone(), expires_at);
+
+    (token, expires_at)
+}
+
+/// 用配对令牌换取登录令牌，配对令牌使用一次后即失效
+pub fn redeem_pairing_token(token: &str) -> Result<LoginResponse, String> {
+    let expires_at = PAIRING_TOKENS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|tokens| tokens.remove(token));
+
+    match expires_at {
+        Some(exp) if exp > now_secs() => auth::issue_token("device"),
+        Some(_) => Err("配对码已过期".to_string()),
+        None => Err("配对码无效或已使用".to_string()),
+    }
+}
+
+/// 将文本编码为二维码，返回 data URL
+fn render_qr_code(data: &str, format: &str) -> Result<String, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| format!("生成二维码失败: {}", e))?;
+
+    match format {
+        "svg" => {
+            let svg = code
+                .render::<svg::Color>()
+                .min_dimensions(QR_CODE_SIZE, QR_CODE_SIZE)
+                .build();
+            Ok(format!(
+                "data:image/svg+xml;base64,{}",
+                general_purpose::STANDARD.encode(svg)
+            ))
+        }
+        "png" => {
+            let img = code
+                .render::<image::Luma<u8>>()
+                .min_dimensions(QR_CODE_SIZE, QR_CODE_SIZE)
+                .build();
+            let png = encode_image(&image::DynamicImage::ImageLuma8(img), OutputFormat::Png, 100)?;
+            Ok(format!(
+                "data:image/png;base64,{}",
+                general_purpose::STANDARD.encode(png)
+            ))
+        }
+        _ => Err(format!("不支持的二维码格式: {}", format)),
+    }
+}
+
+/// 创建配对码：手机扫描二维码打开网页端的配对页面，页面用其中的令牌换取登录令牌
+pub fn create_pairing_code(format: &str) -> Result<PairingCode, String> {
+    let server_url = api_server::network_info().url;
+    let (token, expires_at) = mint_pairing_token();
+    let pairing_url = format!("{}/pair?token={}", server_url, token);
+    let qr_code = render_qr_code(&pairing_url, &format.to_lowercase())?;
+
+    Ok(PairingCode {
+        server_url,
+        token,
+        expires_at,
+        pairing_url,
+        qr_code,
+    })
+}
@@ -0,0 +1,504 @@
+use crate::database::{self, ImageRecord};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 最多遍历的 IFD 数量，防止损坏文件中的循环引用
+const MAX_IFDS: usize = 64;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_IMAGE_WIDTH: u16 = 0x0100;
+const TAG_IMAGE_LENGTH: u16 = 0x0101;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_MAKE: u16 = 0x010F;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const TAG_DNG_VERSION: u16 = 0xC612;
+
+/// 支持的相机 RAW 格式（均基于 TIFF 结构）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// 佳能 CR2
+    Cr2,
+    /// 尼康 NEF
+    Nef,
+    /// 索尼 ARW
+    Arw,
+    /// Adobe DNG
+    Dng,
+}
+
+impl RawFormat {
+    const ALL: [RawFormat; 4] = [RawFormat::Cr2, RawFormat::Nef, RawFormat::Arw, RawFormat::Dng];
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            RawFormat::Cr2 => "image/x-canon-cr2",
+            RawFormat::Nef => "image/x-nikon-nef",
+            RawFormat::Arw => "image/x-sony-arw",
+            RawFormat::Dng => "image/x-adobe-dng",
+        }
+    }
+
+    /// 存储时使用的文件扩展名（含点号）
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RawFormat::Cr2 => ".cr2",
+            RawFormat::Nef => ".nef",
+            RawFormat::Arw => ".arw",
+            RawFormat::Dng => ".dng",
+        }
+    }
+}
+
+/// 是否为 RAW 格式的 MIME 类型
+pub fn is_raw_mime(mime_type: &str) -> bool {
+    RawFormat::ALL.iter().any(|format| format.mime_type() == mime_type)
+}
+
+/// IFD 中的一个条目
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// 条目中 4 字节值/偏移字段的位置
+    value_pos: usize,
+}
+
+/// 只读的 TIFF 结构解析
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let big_endian = if data.starts_with(b"II*\0") {
+            false
+        } else if data.starts_with(b"MM\0*") {
+            true
+        } else {
+            return None;
+        };
+        Some(Tiff { data, big_endian })
+    }
+
+    fn u16_at(&self, pos: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(pos..pos.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn entries(&self, ifd: usize) -> Vec<IfdEntry> {
+        let count = self.u16_at(ifd).unwrap_or(0) as usize;
+        (0..count)
+            .filter_map(|i| {
+                let pos = ifd + 2 + i * 12;
+                Some(IfdEntry {
+                    tag: self.u16_at(pos)?,
+                    field_type: self.u16_at(pos + 2)?,
+                    count: self.u32_at(pos + 4)?,
+                    value_pos: pos + 8,
+                })
+            })
+            .collect()
+    }
+
+    fn next_ifd(&self, ifd: usize) -> Option<usize> {
+        let count = self.u16_at(ifd)? as usize;
+        match self.u32_at(ifd + 2 + count * 12)? {
+            0 => None,
+            offset => Some(offset as usize),
+        }
+    }
+
+    /// 读取整数类型（SHORT/LONG/IFD）条目的第 index 个值
+    fn value(&self, entry: &IfdEntry, index: u32) -> Option<u32> {
+        let size = match entry.field_type {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return None,
+        };
+        if index >= entry.count {
+            return None;
+        }
+        // 总长度不超过 4 字节时值直接存放在条目中
+        let base = if entry.count as usize * size <= 4 {
+            entry.value_pos
+        } else {
+            self.u32_at(entry.value_pos)? as usize
+        };
+        let pos = base.checked_add(index as usize * size)?;
+        match size {
+            2 => self.u16_at(pos).map(u32::from),
+            _ => self.u32_at(pos),
+        }
+    }
+
+    fn ascii(&self, entry: &IfdEntry) -> Option<String> {
+        if entry.field_type != 2 {
+            return None;
+        }
+        let len = entry.count as usize;
+        let start = if len <= 4 {
+            entry.value_pos
+        } else {
+            self.u32_at(entry.value_pos)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+        Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string())
+    }
+
+    fn find(&self, ifd: usize, tag: u16) -> Option<IfdEntry> {
+        self.entries(ifd).into_iter().find(|entry| entry.tag == tag)
+    }
+
+    /// 从 IFD0 开始遍历 IFD 链及其 SubIFD
+    fn all_ifds(&self) -> Vec<usize> {
+        let mut pending: Vec<usize> = self.u32_at(4).map(|offset| offset as usize).into_iter().collect();
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+
+        while let Some(ifd) = pending.pop() {
+            if result.len() >= MAX_IFDS || ifd == 0 || !visited.insert(ifd) {
+                continue;
+            }
+            result.push(ifd);
+
+            if let Some(next) = self.next_ifd(ifd) {
+                pending.push(next);
+            }
+            if let Some(sub_ifds) = self.find(ifd, TAG_SUB_IFDS) {
+                for i in 0..sub_ifds.count.min(MAX_IFDS as u32) {
+                    if let Some(offset) = self.value(&sub_ifds, i) {
+                        pending.push(offset as usize);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// 识别基于 TIFF 结构的 RAW 格式，普通 TIFF 返回 None
+pub fn detect(data: &[u8]) -> Option<RawFormat> {
+    let tiff = Tiff::parse(data)?;
+    if data.get(8..11) == Some(&b"CR\x02"[..]) {
+        return Some(RawFormat::Cr2);
+    }
+
+    let ifd0 = tiff.u32_at(4)? as usize;
+    if tiff.find(ifd0, TAG_DNG_VERSION).is_some() {
+        return Some(RawFormat::Dng);
+    }
+
+    let make = tiff.find(ifd0, TAG_MAKE).and_then(|entry| tiff.ascii(&entry))?;
+    let make = make.to_uppercase();
+    if make.starts_with("NIKON") {
+        Some(RawFormat::Nef)
+    } else if make.starts_with("SONY") {
+        Some(RawFormat::Arw)
+    } else {
+        None
+    }
+}
+
+/// JPEG 的帧类型标记（SOFn），只扫描到第一个 SOF 或 SOS 为止
+fn jpeg_frame_marker(data: &[u8]) -> Option<u8> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    while pos + 3 < data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            0xFF => pos += 1,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => return Some(marker),
+            0xDA | 0xD9 => return None,
+            _ => {
+                let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+                pos += 2 + len;
+            }
+        }
+    }
+    None
+}
+
+/// 提取 RAW 文件中最大的内嵌 JPEG 预览图
+///
+/// 同时检查 JPEGInterchangeFormat 和以 JPEG 压缩的单条带图像，
+/// 排除 RAW 数据本身使用的无损 JPEG（浏览器和解码器都无法显示）。
+pub fn extract_preview(data: &[u8]) -> Option<&[u8]> {
+    let tiff = Tiff::parse(data)?;
+    let mut best: Option<&[u8]> = None;
+
+    for ifd in tiff.all_ifds() {
+        let mut candidates = Vec::new();
+
+        let offset = tiff.find(ifd, TAG_JPEG_OFFSET).and_then(|e| tiff.value(&e, 0));
+        let length = tiff.find(ifd, TAG_JPEG_LENGTH).and_then(|e| tiff.value(&e, 0));
+        if let (Some(offset), Some(length)) = (offset, length) {
+            candidates.push((offset as usize, length as usize));
+        }
+
+        let compression = tiff.find(ifd, TAG_COMPRESSION).and_then(|e| tiff.value(&e, 0));
+        if matches!(compression, Some(6) | Some(7)) {
+            let strips = tiff.find(ifd, TAG_STRIP_OFFSETS);
+            let counts = tiff.find(ifd, TAG_STRIP_BYTE_COUNTS);
+            if let (Some(strips), Some(counts)) = (strips, counts) {
+                if strips.count == 1 {
+                    if let (Some(offset), Some(length)) = (tiff.value(&strips, 0), tiff.value(&counts, 0)) {
+                        candidates.push((offset as usize, length as usize));
+                    }
+                }
+            }
+        }
+
+        for (offset, length) in candidates {
+            let Some(jpeg) = offset
+                .checked_add(length)
+                .and_then(|end| data.get(offset..end))
+            else {
+                continue;
+            };
+            // 只接受基线或渐进式 JPEG
+            if !matches!(jpeg_frame_marker(jpeg), Some(0xC0..=0xC2)) {
+                continue;
+            }
+            if best.map_or(0, |current| current.len()) < jpeg.len() {
+                best = Some(jpeg);
+            }
+        }
+    }
+
+    best
+}
+
+/// IFD0 中记录的 EXIF 方向
+pub fn orientation(data: &[u8]) -> Option<u8> {
+    let tiff = Tiff::parse(data)?;
+    let ifd0 = tiff.u32_at(4)? as usize;
+    let entry = tiff.find(ifd0, TAG_ORIENTATION)?;
+    tiff.value(&entry, 0).and_then(|value| u8::try_from(value).ok())
+}
+
+/// 图片尺寸（未按方向旋转）：取主图像（NewSubFileType 为 0）和内嵌预览图中最大的一个
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let tiff = Tiff::parse(data)?;
+    let mut sizes = Vec::new();
+
+    for ifd in tiff.all_ifds() {
+        let subfile_type = tiff.find(ifd, TAG_NEW_SUBFILE_TYPE).and_then(|e| tiff.value(&e, 0));
+        if subfile_type.unwrap_or(0) != 0 {
+            continue;
+        }
+        let width = tiff.find(ifd, TAG_IMAGE_WIDTH).and_then(|e| tiff.value(&e, 0));
+        let height = tiff.find(ifd, TAG_IMAGE_LENGTH).and_then(|e| tiff.value(&e, 0));
+        if let (Some(width), Some(height)) = (width, height) {
+            sizes.push((width, height));
+        }
+    }
+
+    if let Some(size) = extract_preview(data).and_then(|jpeg| imagesize::blob_size(jpeg).ok()) {
+        sizes.push((size.width as u32, size.height as u32));
+    }
+
+    sizes
+        .into_iter()
+        .filter(|(width, height)| *width > 0 && *height > 0)
+        .max_by_key(|(width, height)| *width as u64 * *height as u64)
+}
+
+/// 文件名去掉扩展名后的部分（不区分大小写）
+fn basename(filename: &str) -> String {
+    Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 将 RAW 与同名的 JPEG 关联为一组，返回配对的另一张图片 ID
+///
+/// 以 JPEG 为主图，RAW 记录指向 JPEG，列表中只显示 JPEG。
+/// 两者都有拍摄时间时必须一致，避免不同相机的同名文件被误配对。
+pub fn link_pair(image_id: i32) -> Result<Option<i32>, String> {
+    let record = database::get_image_by_id(image_id)
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+    if record.paired_id.is_some() {
+        return Ok(record.paired_id);
+    }
+
+    let is_raw = match record.mime_type.as_deref() {
+        Some(mime) if is_raw_mime(mime) => true,
+        Some("image/jpeg") => false,
+        _ => return Ok(None),
+    };
+
+    let name = basename(&record.filename);
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    let candidates = database::find_unpaired_images_by_basename(image_id, &name)
+        .map_err(|e| format!("查询同名图片失败: {}", e))?;
+
+    let taken_at = |r: &ImageRecord| r.metadata.as_ref().and_then(|m| m.exif_datetime.clone());
+    let partner = candidates.into_iter().find(|candidate| {
+        let mime = candidate.mime_type.as_deref().unwrap_or("");
+        let kind_matches = if is_raw {
+            mime == "image/jpeg"
+        } else {
+            is_raw_mime(mime)
+        };
+        let same_time = match (taken_at(&record), taken_at(candidate)) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        kind_matches && same_time && basename(&candidate.filename) == name
+    });
+
+    let Some(partner) = partner else {
+        return Ok(None);
+    };
+
+    let (raw_id, jpeg_id) = if is_raw {
+        (record.id, partner.id)
+    } else {
+        (partner.id, record.id)
+    };
+    database::set_paired_image(raw_id, jpeg_id)
+        .map_err(|e| format!("关联 RAW 与 JPEG 失败: {}", e))?;
+
+    Ok(Some(partner.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 最小的基线 JPEG：SOI、SOF0（1x1 灰度）、EOI
+    const JPEG: [u8; 17] = [
+        0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0x01, 0x00, 0x01, 0x01, 0x01, 0x11, 0x00,
+        0xFF, 0xD9,
+    ];
+
+    /// 小端 TIFF：IFD0 位于偏移 8，entries 为 (标签, 类型, 数量, 值)，tail 紧跟在 IFD 之后
+    fn tiff(entries: &[(u16, u16, u32, u32)], next_ifd: u32, tail: &[u8]) -> Vec<u8> {
+        let mut data = b"II*\0".to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend((entries.len() as u16).to_le_bytes());
+        for (tag, field_type, count, value) in entries {
+            data.extend(tag.to_le_bytes());
+            data.extend(field_type.to_le_bytes());
+            data.extend(count.to_le_bytes());
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(next_ifd.to_le_bytes());
+        data.extend(tail);
+        data
+    }
+
+    /// IFD0 带方向标签，JPEGInterchangeFormat 指向紧跟在 IFD 之后的预览图
+    fn raw_with_preview(offset: u32, length: u32) -> Vec<u8> {
+        tiff(
+            &[
+                (TAG_ORIENTATION, 3, 1, 6),
+                (TAG_JPEG_OFFSET, 4, 1, offset),
+                (TAG_JPEG_LENGTH, 4, 1, length),
+            ],
+            0,
+            &JPEG,
+        )
+    }
+
+    const PREVIEW_OFFSET: u32 = 8 + 2 + 3 * 12 + 4;
+
+    #[test]
+    fn extracts_embedded_preview() {
+        let data = raw_with_preview(PREVIEW_OFFSET, JPEG.len() as u32);
+        assert_eq!(extract_preview(&data), Some(&JPEG[..]));
+        assert_eq!(orientation(&data), Some(6));
+        assert_eq!(dimensions(&data), Some((1, 1)));
+    }
+
+    #[test]
+    fn out_of_range_preview_offsets_are_rejected() {
+        let too_long = raw_with_preview(PREVIEW_OFFSET, JPEG.len() as u32 + 1);
+        assert_eq!(extract_preview(&too_long), None);
+
+        let past_end = raw_with_preview(u32::MAX, JPEG.len() as u32);
+        assert_eq!(extract_preview(&past_end), None);
+
+        let overflowing = raw_with_preview(PREVIEW_OFFSET, u32::MAX);
+        assert_eq!(extract_preview(&overflowing), None);
+    }
+
+    #[test]
+    fn out_of_range_ifd_offsets_are_rejected() {
+        let mut data = raw_with_preview(PREVIEW_OFFSET, JPEG.len() as u32);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(extract_preview(&data), None);
+        assert_eq!(orientation(&data), None);
+        assert_eq!(dimensions(&data), None);
+        assert_eq!(detect(&data), None);
+
+        // 条目值的偏移超出文件末尾
+        let data = tiff(&[(TAG_MAKE, 2, 16, 0xFFFF_FF00)], 0, &[]);
+        assert_eq!(detect(&data), None);
+        let data = tiff(&[(TAG_SUB_IFDS, 4, u32::MAX, 0xFFFF_FF00)], 0, &[]);
+        assert_eq!(extract_preview(&data), None);
+    }
+
+    #[test]
+    fn truncated_files_never_panic() {
+        let data = raw_with_preview(PREVIEW_OFFSET, JPEG.len() as u32);
+        for len in 0..data.len() {
+            let truncated = &data[..len];
+            assert_eq!(extract_preview(truncated), None);
+            let _ = orientation(truncated);
+            let _ = dimensions(truncated);
+            let _ = detect(truncated);
+        }
+    }
+
+    #[test]
+    fn cyclic_ifd_chains_terminate() {
+        // 下一个 IFD 和 SubIFD 都指回 IFD0
+        let data = tiff(&[(TAG_SUB_IFDS, 4, 1, 8)], 8, &[]);
+        assert_eq!(extract_preview(&data), None);
+        assert_eq!(dimensions(&data), None);
+    }
+
+    #[test]
+    fn detects_raw_formats() {
+        let make = b"NIKON CORPORATION\0";
+        let offset = 8 + 2 + 12 + 4;
+        let data = tiff(&[(TAG_MAKE, 2, make.len() as u32, offset)], 0, make);
+        assert_eq!(detect(&data), Some(RawFormat::Nef));
+
+        let data = tiff(&[(TAG_DNG_VERSION, 1, 4, 0x0000_0401)], 0, &[]);
+        assert_eq!(detect(&data), Some(RawFormat::Dng));
+
+        assert_eq!(detect(&tiff(&[], 0, &[])), None);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::config;
+use crate::database::ImageRecord;
+use crate::image::{encode_image, open_image, OutputFormat};
+use crate::upload::write_file_atomic;
+use image::imageops::FilterType;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// 缩放图允许的最大边长
+const MAX_RENDITION_SIZE: u32 = 8192;
+
+/// 默认编码质量
+const DEFAULT_QUALITY: u8 = 85;
+
+/// 缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitMode {
+    /// 等比缩放至完全放入目标尺寸内
+    Contain,
+    /// 等比缩放至铺满目标尺寸，超出部分居中裁剪
+    Cover,
+    /// 拉伸至目标尺寸，不保持宽高比
+    Fill,
+}
+
+impl FitMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FitMode::Contain => "contain",
+            FitMode::Cover => "cover",
+            FitMode::Fill => "fill",
+        }
+    }
+}
+
+/// 缩放图请求参数（对应 URL 查询参数）
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RenditionParams {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Option<String>,
+    pub format: Option<String>,
+    pub quality: Option<u8>,
+}
+
+/// 校验后的缩放图规格
+#[derive(Debug, Clone, Copy)]
+pub struct RenditionSpec {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: FitMode,
+    /// None 表示沿用原图格式
+    pub format: Option<OutputFormat>,
+    pub quality: u8,
+}
+
+impl RenditionParams {
+    /// 是否未指定任何参数（此时应直接返回原图）
+    pub fn is_empty(&self) -> bool {
+        self.width.is_none()
+            && self.height.is_none()
+            && self.fit.is_none()
+            && self.format.is_none()
+            && self.quality.is_none()
+    }
+
+    /// 校验参数并转换为缩放图规格
+    pub fn parse(&self) -> Result<RenditionSpec, String> {
+        for size in [self.width, self.height].into_iter().flatten() {
+            if size == 0 || size > MAX_RENDITION_SIZE {
+                return Err(format!("宽高必须在 1 到 {} 之间", MAX_RENDITION_SIZE));
+            }
+        }
+
+        let fit = match self.fit.as_deref().map(|f| f.to_lowercase()) {
+            None => FitMode::Contain,
+            Some(f) => match f.as_str() {
+                "contain" => FitMode::Contain,
+                "cover" => FitMode::Cover,
+                "fill" => FitMode::Fill,
+                _ => return Err(format!("不支持的缩放方式: {}", f)),
+            },
+        };
+
+        let format = match self.format.as_deref().map(|f| f.to_lowercase()) {
+            None => None,
+            Some(f) => match f.as_str() {
+                "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+                "png" => Some(OutputFormat::Png),
+                "webp" => Some(OutputFormat::WebP),
+                _ => return Err(format!("不支持的输出格式: {}", f)),
+            },
+        };
+
+        let quality = match self.quality {
+            None => DEFAULT_QUALITY,
+            Some(q) if (1..=100).contains(&q) => q,
+            Some(_) => return Err("质量参数必须在 1 到 100 之间".to_string()),
+        };
+
+        Ok(RenditionSpec {
+            width: self.width,
+            height: self.height,
+            fit,
+            format,
+            quality,
+        })
+    }
+}
+
+/// 根据原图扩展名推断默认输出格式，无法直接输出的格式统一转为 JPEG
+fn default_output_format(source: &Path) -> OutputFormat {
+    match source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => OutputFormat::Png,
+        Some("webp") => OutputFormat::WebP,
+        _ => OutputFormat::Jpeg,
+    }
+}
+
+/// 缩放图缓存路径：以图片哈希和参数为键
+fn get_rendition_cache_path(hash: &str, spec: &RenditionSpec, format: OutputFormat) -> PathBuf {
+    let mut path = config::get_renditions_dir();
+    let prefix = &hash[..hash.len().min(2)];
+    path.push(prefix);
+    path.push(format!(
+        "{}_{}x{}_{}_q{}{}",
+        hash,
+        spec.width.unwrap_or(0),
+        spec.height.unwrap_or(0),
+        spec.fit.as_str(),
+        spec.quality,
+        format.extension()
+    ));
+    path
+}
+
+/// 获取缩放图，缓存中不存在时生成并写入缓存
+pub fn get_or_create_rendition(record: &ImageRecord, spec: &RenditionSpec) -> Result<PathBuf, String> {
+    let source = Path::new(&record.path);
+    let format = spec.format.unwrap_or_else(|| default_output_format(source));
+
+    let cache_path = get_rendition_cache_path(&record.hash, spec, format);
+    if cache_path.exists() {
+        return Ok(cache_path);
+    }
+
+    if !source.exists() {
+        return Err(format!("文件不存在: {}", record.path));
+    }
+
+    let img = open_image(source)?;
+    let (orig_width, orig_height) = (img.width(), img.height());
+
+    let resized = match (spec.width, spec.height) {
+        (None, None) => img,
+        (Some(w), Some(h)) => match spec.fit {
+            FitMode::Contain => {
+                // 不放大超过原图尺寸
+                if w >= orig_width && h >= orig_height {
+                    img
+                } else {
+                    img.resize(w, h, FilterType::Lanczos3)
+                }
+            }
+            FitMode::Cover => img.resize_to_fill(w, h, FilterType::Lanczos3),
+            FitMode::Fill => img.resize_exact(w, h, FilterType::Lanczos3),
+        },
+        // 只指定一边时按原图宽高比缩放
+        (Some(w), None) => {
+            if w >= orig_width {
+                img
+            } else {
+                img.resize(w, u32::MAX, FilterType::Lanczos3)
+            }
+        }
+        (None, Some(h)) => {
+            if h >= orig_height {
+                img
+            } else {
+                img.resize(u32::MAX, h, FilterType::Lanczos3)
+            }
+        }
+    };
+
+    let data = encode_image(&resized, format, spec.quality)?;
+    write_file_atomic(&cache_path, &data)?;
+
+    Ok(cache_path)
+}
+
+/// 删除图片的所有缓存缩放图，返回删除的文件数量
+pub fn remove_renditions(hash: &str) -> usize {
+    let dir = config::get_renditions_dir().join(&hash[..hash.len().min(2)]);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return 0;
+    };
+
+    let prefix = format!("{}_", hash);
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
@@ -0,0 +1,219 @@
+use crate::image::validate_image_file;
+use crate::upload::{self, UploadResult};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 单个断点续传上传允许的最大文件大小（4 GB）
+pub const MAX_UPLOAD_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// 超过该时间没有新数据的上传会被清理
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 3600);
+
+/// 正在写入数据的上传，防止同一上传的多个请求并发写入
+static ACTIVE_UPLOADS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// 断点续传上传会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    pub filename: String,
+    /// 文件总大小
+    pub size: u64,
+    /// 已接收的字节数（以临时文件实际大小为准）
+    pub offset: u64,
+    pub created_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 上传 ID 只允许十六进制字符，避免拼接路径时越界
+fn validate_id(id: &str) -> Result<(), String> {
+    if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err("无效的上传 ID".to_string())
+    }
+}
+
+fn session_path(id: &str) -> PathBuf {
+    upload::get_upload_temp_dir().join(format!("{}.json", id))
+}
+
+/// 上传数据的临时文件路径
+pub fn data_path(id: &str) -> PathBuf {
+    upload::get_upload_temp_dir().join(format!("{}.upload", id))
+}
+
+fn save_session(session: &UploadSession) -> Result<(), String> {
+    let json = serde_json::to_vec(session)
+        .map_err(|e| format!("序列化上传信息失败: {}", e))?;
+    upload::write_file_atomic(&session_path(&session.id), &json)
+}
+
+/// 创建上传会话
+pub fn create_upload(filename: &str, size: u64) -> Result<UploadSession, String> {
+    let filename = Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "文件名不能为空".to_string())?;
+
+    if size == 0 || size > MAX_UPLOAD_SIZE {
+        return Err(format!("文件大小必须在 1 到 {} 字节之间", MAX_UPLOAD_SIZE));
+    }
+
+    // 顺便清理过期的上传
+    if let Err(e) = cleanup_stale_uploads() {
+        eprintln!("清理过期上传失败: {}", e);
+    }
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let session = UploadSession {
+        id: hex::encode(bytes),
+        filename,
+        size,
+        offset: 0,
+        created_at: now_secs(),
+    };
+
+    fs::create_dir_all(upload::get_upload_temp_dir())
+        .map_err(|e| format!("创建上传临时目录失败: {}", e))?;
+    fs::File::create(data_path(&session.id))
+        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+    save_session(&session)?;
+
+    Ok(session)
+}
+
+/// 查询上传会话，offset 为临时文件当前大小
+pub fn get_upload(id: &str) -> Result<UploadSession, String> {
+    validate_id(id)?;
+
+    let json = fs::read(session_path(id)).map_err(|_| "上传不存在或已过期".to_string())?;
+    let mut session: UploadSession = serde_json::from_slice(&json)
+        .map_err(|e| format!("读取上传信息失败: {}", e))?;
+
+    session.offset = fs::metadata(data_path(id))
+        .map(|meta| meta.len())
+        .map_err(|_| "上传不存在或已过期".to_string())?;
+
+    Ok(session)
+}
+
+/// 标记上传正在写入，返回 false 表示已有其他请求在写入
+pub fn begin_write(id: &str) -> bool {
+    let mut active = ACTIVE_UPLOADS.lock().unwrap();
+    active.get_or_insert_with(HashSet::new).insert(id.to_string())
+}
+
+pub fn end_write(id: &str) {
+    if let Some(active) = ACTIVE_UPLOADS.lock().unwrap().as_mut() {
+        active.remove(id);
+    }
+}
+
+fn remove_upload_files(id: &str) {
+    let _ = fs::remove_file(data_path(id));
+    let _ = fs::remove_file(session_path(id));
+}
+
+/// 完成上传：数据接收完整后导入图库，并删除临时文件
+///
+/// 文件校验失败时删除上传；导入时的其他错误保留上传，可以再次调用重试。
+pub fn finalize_upload(id: &str) -> Result<UploadResult, String> {
+    let session = get_upload(id)?;
+    if session.offset != session.size {
+        return Err(format!(
+            "上传尚未完成: 已接收 {} / {} 字节",
+            session.offset, session.size
+        ));
+    }
+
+    if !begin_write(id) {
+        return Err("该上传正在写入数据".to_string());
+    }
+
+    let path = data_path(id);
+    let result = match validate_image_file(&path) {
+        Ok(format) => upload::import_validated_image_file(&path, &session.filename, format),
+        Err(e) => {
+            // 格式不支持或文件损坏，重新上传也无法成功
+            remove_upload_files(id);
+            end_write(id);
+            return Err(format!("图片格式验证失败: {}", e));
+        }
+    };
+
+    // 数据库或磁盘等临时错误时保留已接收的数据，客户端可以直接重试完成上传
+    if result.is_ok() {
+        remove_upload_files(id);
+    }
+    end_write(id);
+    result
+}
+
+/// 取消上传
+pub fn cancel_upload(id: &str) -> Result<(), String> {
+    validate_id(id)?;
+    if !begin_write(id) {
+        return Err("该上传正在写入数据".to_string());
+    }
+    remove_upload_files(id);
+    end_write(id);
+    Ok(())
+}
+
+/// 清理长时间没有新数据的上传临时文件，返回清理的文件数量
+pub fn cleanup_stale_uploads() -> Result<usize, String> {
+    let dir = upload::get_upload_temp_dir();
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| format!("读取上传临时目录失败: {}", e))?;
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        // 正在写入的上传不清理
+        let active = ACTIVE_UPLOADS
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|active| active.contains(id));
+        if active {
+            continue;
+        }
+
+        // 上传会话以数据文件最后写入的时间为准，其他临时文件按自身修改时间
+        let data = data_path(id);
+        let reference = if data.exists() { data } else { path.clone() };
+        let stale = fs::metadata(&reference)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > UPLOAD_EXPIRY);
+
+        if stale && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
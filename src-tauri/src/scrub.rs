@@ -0,0 +1,259 @@
+use crate::config;
+use crate::database;
+use crate::upload::calculate_file_hash;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// 发现文件内容与记录的哈希不一致时发出的事件名
+pub const MISMATCH_EVENT: &str = "scrub-mismatch";
+
+/// 后台检查是否还有当天校验额度的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 每校验一个文件后暂停，避免长时间占满磁盘读取
+const FILE_PAUSE: Duration = Duration::from_millis(50);
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 同一时间只允许一个校验任务
+static SCRUB_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 校验结果，存入 images.verify_status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    Ok,
+    /// 文件内容的哈希与记录不一致
+    Mismatch,
+    /// 文件不存在
+    Missing,
+    /// 文件存在但读取失败（权限或 I/O 错误）
+    Error,
+}
+
+impl VerifyStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerifyStatus::Ok => "ok",
+            VerifyStatus::Mismatch => "mismatch",
+            VerifyStatus::Missing => "missing",
+            VerifyStatus::Error => "error",
+        }
+    }
+}
+
+/// 校验失败的图片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubFailure {
+    pub image_id: i32,
+    pub filename: String,
+    pub path: String,
+    pub status: VerifyStatus,
+    pub expected_hash: String,
+    pub actual_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScrubReport {
+    /// 校验的图片数量
+    pub checked: usize,
+    /// 哈希一致的数量
+    pub ok: usize,
+    pub failed: Vec<ScrubFailure>,
+}
+
+/// 重新计算最久未校验的 limit 张图片的哈希并记录结果，每发现一个问题调用一次 on_failure
+pub fn scrub_batch<F>(limit: usize, on_failure: F) -> Result<ScrubReport, String>
+where
+    F: Fn(&ScrubFailure),
+{
+    if SCRUB_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("已有校验任务正在进行".to_string());
+    }
+
+    let result = run_batch(limit, on_failure);
+    SCRUB_RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+fn run_batch<F>(limit: usize, on_failure: F) -> Result<ScrubReport, String>
+where
+    F: Fn(&ScrubFailure),
+{
+    let records = database::get_images_to_verify(limit)
+        .map_err(|e| format!("查询待校验图片失败: {}", e))?;
+
+    let mut report = ScrubReport::default();
+    for record in records {
+        let path = Path::new(&record.path);
+        let (status, actual_hash, error) = if !path.exists() {
+            (VerifyStatus::Missing, None, None)
+        } else {
+            match calculate_file_hash(path) {
+                Ok(hash) if hash == record.hash => (VerifyStatus::Ok, Some(hash), None),
+                Ok(hash) => (VerifyStatus::Mismatch, Some(hash), None),
+                Err(e) => (VerifyStatus::Error, None, Some(e)),
+            }
+        };
+
+        database::record_verification(
+            record.id,
+            status.as_str(),
+            actual_hash.as_deref(),
+            error.as_deref(),
+        )
+        .map_err(|e| format!("保存校验结果失败: {}", e))?;
+        report.checked += 1;
+
+        if status == VerifyStatus::Ok {
+            report.ok += 1;
+        } else {
+            let failure = ScrubFailure {
+                image_id: record.id,
+                filename: record.filename,
+                path: record.path,
+                status,
+                expected_hash: record.hash,
+                actual_hash,
+                error,
+            };
+            on_failure(&failure);
+            report.failed.push(failure);
+        }
+
+        thread::sleep(FILE_PAUSE);
+    }
+
+    Ok(report)
+}
+
+/// 校验当天剩余额度内的图片（额度为配置的 scrub_daily_limit 减去最近 24 小时已校验的数量）
+pub fn scrub_due<F>(on_failure: F) -> Result<ScrubReport, String>
+where
+    F: Fn(&ScrubFailure),
+{
+    let daily_limit = config::load_config().scrub_daily_limit as i64;
+    let verified = database::count_recently_verified()
+        .map_err(|e| format!("查询校验记录失败: {}", e))?;
+
+    let remaining = (daily_limit - verified).max(0) as usize;
+    if remaining == 0 {
+        return Ok(ScrubReport::default());
+    }
+    scrub_batch(remaining, on_failure)
+}
+
+/// 启动后台线程定期校验图片哈希（只启动一次）
+pub fn start_scheduler<F>(on_failure: F)
+where
+    F: Fn(&ScrubFailure) + Send + 'static,
+{
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(move || loop {
+        match scrub_due(&on_failure) {
+            Ok(report) if !report.failed.is_empty() => {
+                eprintln!("哈希校验发现 {} 张图片异常（共校验 {} 张）", report.failed.len(), report.checked);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("定期哈希校验失败: {}", e),
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+    use std::fs;
+    use std::sync::Mutex;
+
+    #[test]
+    fn scrub_records_ok_mismatch_missing_and_error() {
+        let library = TestLibrary::new();
+        let ok = library.import_png("ok.png", [200, 30, 30]);
+        let mismatch = library.import_png("mismatch.png", [30, 200, 30]);
+        let missing = library.import_png("missing.png", [30, 30, 200]);
+        let unreadable = library.import_png("unreadable.png", [200, 200, 30]);
+
+        fs::write(database::get_image_by_id(mismatch).unwrap().path, b"changed").unwrap();
+        fs::remove_file(database::get_image_by_id(missing).unwrap().path).unwrap();
+        // 用同名目录代替文件，使读取失败
+        let unreadable_path = database::get_image_by_id(unreadable).unwrap().path;
+        fs::remove_file(&unreadable_path).unwrap();
+        fs::create_dir(&unreadable_path).unwrap();
+
+        let reported = Mutex::new(Vec::new());
+        let report = scrub_batch(10, |failure| {
+            reported.lock().unwrap().push((failure.image_id, failure.status))
+        })
+        .unwrap();
+
+        assert_eq!(report.checked, 4);
+        assert_eq!(report.ok, 1);
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort_by_key(|(id, _)| *id);
+        assert_eq!(
+            reported,
+            [
+                (mismatch, VerifyStatus::Mismatch),
+                (missing, VerifyStatus::Missing),
+                (unreadable, VerifyStatus::Error),
+            ]
+        );
+
+        for (id, status) in [
+            (ok, VerifyStatus::Ok),
+            (mismatch, VerifyStatus::Mismatch),
+            (missing, VerifyStatus::Missing),
+            (unreadable, VerifyStatus::Error),
+        ] {
+            let record = database::get_image_by_id(id).unwrap();
+            assert_eq!(record.verify_status.as_deref(), Some(status.as_str()));
+            assert!(record.verified_at.is_some());
+
+            let history = database::get_verification_history(id).unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].status, status.as_str());
+        }
+        let history = database::get_verification_history(unreadable).unwrap();
+        assert!(history[0].error.is_some());
+        let history = database::get_verification_history(mismatch).unwrap();
+        assert!(history[0].actual_hash.is_some());
+    }
+
+    #[test]
+    fn scrub_due_stops_at_the_daily_limit() {
+        let library = TestLibrary::new();
+        config::save_config(&config::AppConfig {
+            scrub_daily_limit: 2,
+            ..config::load_config()
+        })
+        .unwrap();
+        let ids: Vec<i32> = [[10, 10, 10], [20, 20, 20], [30, 30, 30]]
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| library.import_png(&format!("{}.png", i), color))
+            .collect();
+
+        assert_eq!(scrub_due(|_| {}).unwrap().checked, 2);
+        assert_eq!(scrub_due(|_| {}).unwrap().checked, 0);
+
+        // 从未校验过的图片优先
+        let unverified: Vec<i32> = ids
+            .into_iter()
+            .filter(|&id| database::get_image_by_id(id).unwrap().verified_at.is_none())
+            .collect();
+        assert_eq!(unverified.len(), 1);
+        let report = scrub_batch(1, |_| {}).unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(database::get_image_by_id(unverified[0]).unwrap().verified_at.is_some());
+    }
+}
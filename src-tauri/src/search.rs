@@ -0,0 +1,875 @@
+use chrono::{Months, NaiveDate};
+use pinyin::ToPinyin;
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 生成拼音后缀时单个汉字串的最大长度
+const MAX_PINYIN_RUN: usize = 12;
+
+/// 是否生成拼音索引，启动和修改配置时更新，打开数据库连接时不再读取配置文件
+static PINYIN_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 宽高比与 1 相差不超过该值时视为方图
+const SQUARE_TOLERANCE: f64 = 0.01;
+
+/// 解析后的搜索查询
+///
+/// 支持的语法（各条件之间为 AND 关系，前缀 `-` 表示排除）：
+///
+/// - 普通词：前缀匹配文件名、描述、标签、EXIF 文本，如 `sunset`
+/// - 双引号短语：`"sunset beach"`
+/// - `tag:travel`：带有指定标签
+/// - `camera:"Canon EOS"`：相机厂商或型号包含指定文本
+/// - `name:IMG_`：文件名包含指定文本
+/// - `ext:png` / `ext:jpg,png`：文件扩展名
+/// - `size:>5MB`、`size:<=500KB`、`size:1MB..10MB`：文件大小
+/// - `date:2024`、`date:2024-01..2024-06`、`date:>=2024-03-01`：拍摄时间（无 EXIF 时使用导入时间）
+/// - `width:>=1920`、`height:1000..2000`：图片宽度、高度（像素）
+/// - `res:1920x1080`：最低分辨率，宽高都不小于指定值
+/// - `orientation:landscape`：图片方向，可选 landscape（横图）、portrait（竖图）、square（方图）
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    /// 全文检索表达式（由普通词和短语生成）
+    pub fts_query: Option<String>,
+    /// 附加的 SQL 条件，引用 images 别名 i、image_metadata 别名 m
+    pub conditions: Vec<String>,
+    /// 与 conditions 中占位符 ? 依次对应的参数
+    pub params: Vec<Value>,
+}
+
+impl SearchQuery {
+    /// 将附加条件拼接为 WHERE 子句片段（以 AND 连接），没有条件时返回 "1"
+    pub fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            "1".to_string()
+        } else {
+            self.conditions.join(" AND ")
+        }
+    }
+
+    fn push_condition(&mut self, negated: bool, sql: &str, params: Vec<Value>) {
+        if negated {
+            self.conditions.push(format!("NOT ({})", sql));
+        } else {
+            self.conditions.push(sql.to_string());
+        }
+        self.params.extend(params);
+    }
+}
+
+/// 图片方向（按宽高比判断）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+impl Orientation {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "landscape" | "横图" => Some(Orientation::Landscape),
+            "portrait" | "竖图" => Some(Orientation::Portrait),
+            "square" | "方图" => Some(Orientation::Square),
+            _ => None,
+        }
+    }
+
+    /// 对应的 SQL 条件，缺少尺寸信息的图片不匹配任何方向
+    fn condition(&self) -> (&'static str, Vec<Value>) {
+        match self {
+            Orientation::Landscape => ("i.aspect_ratio > ?", vec![Value::Real(1.0 + SQUARE_TOLERANCE)]),
+            Orientation::Portrait => ("i.aspect_ratio < ?", vec![Value::Real(1.0 - SQUARE_TOLERANCE)]),
+            Orientation::Square => (
+                "i.aspect_ratio BETWEEN ? AND ?",
+                vec![Value::Real(1.0 - SQUARE_TOLERANCE), Value::Real(1.0 + SQUARE_TOLERANCE)],
+            ),
+        }
+    }
+}
+
+/// 列表的尺寸筛选条件（与搜索语法中的 res:、orientation: 等价）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DimensionFilter {
+    /// 最小宽度（像素）
+    pub min_width: Option<u32>,
+    /// 最小高度（像素）
+    pub min_height: Option<u32>,
+    pub orientation: Option<Orientation>,
+}
+
+impl DimensionFilter {
+    /// 将筛选条件追加到查询中
+    pub fn apply(&self, query: &mut SearchQuery) {
+        if let Some(min_width) = self.min_width {
+            query.push_condition(false, "i.width >= ?", vec![Value::Integer(min_width as i64)]);
+        }
+        if let Some(min_height) = self.min_height {
+            query.push_condition(false, "i.height >= ?", vec![Value::Integer(min_height as i64)]);
+        }
+        if let Some(orientation) = self.orientation {
+            let (sql, params) = orientation.condition();
+            query.push_condition(false, sql, params);
+        }
+    }
+}
+
+/// 查询中的一个词
+struct QueryToken {
+    negated: bool,
+    key: Option<String>,
+    value: String,
+    /// 值是否由双引号包裹
+    quoted: bool,
+}
+
+/// 解析搜索输入，字段条件的值不合法时返回错误
+pub fn parse_query(input: &str) -> Result<SearchQuery, String> {
+    let mut query = SearchQuery::default();
+    let mut fts_terms = Vec::new();
+
+    for token in tokenize(input) {
+        let key = token.key.as_deref().map(str::to_lowercase);
+        match key.as_deref() {
+            Some("tag") => {
+                query.push_condition(
+                    token.negated,
+                    "EXISTS (SELECT 1 FROM image_tags t WHERE t.image_id = i.id AND t.tag = ?)",
+                    vec![Value::Text(token.value.trim().to_string())],
+                );
+            }
+            Some("camera") => {
+                query.push_condition(
+                    token.negated,
+                    "(COALESCE(m.exif_make, '') || ' ' || COALESCE(m.exif_model, '')) LIKE ? ESCAPE '\\'",
+                    vec![Value::Text(like_pattern(&token.value))],
+                );
+            }
+            Some("name") => {
+                query.push_condition(
+                    token.negated,
+                    "i.filename LIKE ? ESCAPE '\\'",
+                    vec![Value::Text(like_pattern(&token.value))],
+                );
+            }
+            Some("ext") => {
+                let mut clauses = Vec::new();
+                let mut params = Vec::new();
+                for ext in extension_aliases(&token.value) {
+                    clauses.push("lower(i.path) LIKE ?");
+                    params.push(Value::Text(format!("%.{}", ext)));
+                }
+                if clauses.is_empty() {
+                    return Err(format!("无效的扩展名条件: {}", token.value));
+                }
+                query.push_condition(token.negated, &format!("({})", clauses.join(" OR ")), params);
+            }
+            Some("size") => {
+                let (sql, params) = parse_size_filter(&token.value)?;
+                query.push_condition(token.negated, &sql, params);
+            }
+            Some("date") => {
+                let (sql, params) = parse_date_filter(&token.value)?;
+                query.push_condition(token.negated, &sql, params);
+            }
+            Some("width") => {
+                let (sql, params) = parse_dimension_filter("i.width", &token.value)?;
+                query.push_condition(token.negated, &sql, params);
+            }
+            Some("height") => {
+                let (sql, params) = parse_dimension_filter("i.height", &token.value)?;
+                query.push_condition(token.negated, &sql, params);
+            }
+            Some("res") => {
+                let (width, height) = parse_resolution(&token.value)?;
+                query.push_condition(
+                    token.negated,
+                    "(i.width >= ? AND i.height >= ?)",
+                    vec![Value::Integer(width), Value::Integer(height)],
+                );
+            }
+            Some("orientation") => {
+                let orientation = Orientation::parse(&token.value)
+                    .ok_or_else(|| format!("无效的图片方向: {}", token.value))?;
+                let (sql, params) = orientation.condition();
+                query.push_condition(token.negated, sql, params);
+            }
+            _ => {
+                // 未知字段按普通文本处理，如 "12:30"
+                let text = match &token.key {
+                    Some(key) => format!("{}:{}", key, token.value),
+                    None => token.value.clone(),
+                };
+                let Some(term) = fts_term(&text, token.quoted) else {
+                    continue;
+                };
+                if token.negated {
+                    query.push_condition(
+                        false,
+                        "i.id NOT IN (SELECT rowid FROM images_fts WHERE images_fts MATCH ?)",
+                        vec![Value::Text(term)],
+                    );
+                } else {
+                    fts_terms.push(term);
+                }
+            }
+        }
+    }
+
+    if !fts_terms.is_empty() {
+        query.fts_query = Some(fts_terms.join(" "));
+    }
+
+    Ok(query)
+}
+
+/// 按空白切分查询，支持 `-` 排除前缀、`key:value` 字段和双引号包裹的值
+fn tokenize(input: &str) -> Vec<QueryToken> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut negated = false;
+        if c == '-' {
+            negated = true;
+            chars.next();
+        }
+
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+
+            if c == '"' {
+                // 引号内的内容原样读取（缺少结束引号时读到末尾）
+                quoted = true;
+                value.extend(chars.by_ref().take_while(|&c| c != '"'));
+            } else if c == ':' && key.is_none() && !quoted && !value.is_empty() {
+                key = Some(std::mem::take(&mut value));
+            } else {
+                value.push(c);
+            }
+        }
+
+        // 单独的 "-" 或 "key:" 没有意义，忽略
+        if value.trim().is_empty() {
+            continue;
+        }
+
+        tokens.push(QueryToken { negated, key, value, quoted });
+    }
+
+    tokens
+}
+
+/// 将一个普通词或短语转换为 FTS5 查询项
+///
+/// - 短语精确匹配，普通词按前缀匹配，如 `cat` 匹配 `cats`
+/// - 中日韩文字切分为二元词后作为短语匹配，`风景` 只匹配相邻的 "风景"，
+///   以汉字结尾时总是前缀匹配，单字 `风` 也能命中 "风景"
+///
+/// 所有词都会被转义为 FTS5 字符串，用户输入中的运算符不会破坏查询语法。
+fn fts_term(text: &str, phrase: bool) -> Option<String> {
+    let text = text.trim();
+    if !has_searchable_chars(text) {
+        return None;
+    }
+
+    let quoted = quote_fts_string(&segment_cjk_query(text));
+    if phrase && !text.chars().last().is_some_and(is_cjk) {
+        Some(quoted)
+    } else {
+        Some(format!("{}*", quoted))
+    }
+}
+
+/// 生成 LIKE 子串匹配模式，转义通配符
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .trim()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// 解析扩展名列表，jpg 与 jpeg、tif 与 tiff 互为别名
+fn extension_aliases(value: &str) -> Vec<String> {
+    let mut result = Vec::new();
+
+    for ext in value.split(',') {
+        let ext = ext.trim().trim_start_matches('.').to_lowercase();
+        if ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+            continue;
+        }
+
+        let aliases: Vec<String> = match ext.as_str() {
+            "jpg" | "jpeg" => vec!["jpg".into(), "jpeg".into()],
+            "tif" | "tiff" => vec!["tif".into(), "tiff".into()],
+            "heic" | "heif" => vec!["heic".into(), "heif".into()],
+            _ => vec![ext],
+        };
+        for alias in aliases {
+            if !result.contains(&alias) {
+                result.push(alias);
+            }
+        }
+    }
+
+    result
+}
+
+/// 比较运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+}
+
+/// 拆分比较运算符前缀，如 ">=5MB" -> (Ge, "5MB")
+fn split_comparison(value: &str) -> (Comparison, &str) {
+    let value = value.trim();
+    for (prefix, cmp) in [
+        (">=", Comparison::Ge),
+        ("<=", Comparison::Le),
+        (">", Comparison::Gt),
+        ("<", Comparison::Lt),
+        ("=", Comparison::Eq),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (cmp, rest.trim());
+        }
+    }
+    (Comparison::Eq, value)
+}
+
+/// 解析文件大小，如 "5MB"、"1.5GB"、"500k"、"1024"（单位为 1024 进制）
+fn parse_size(text: &str) -> Result<i64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("无效的文件大小: {}", text))?;
+
+    let multiplier: f64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1024.0,
+        "m" | "mb" => 1024.0 * 1024.0,
+        "g" | "gb" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("无效的文件大小单位: {}", unit)),
+    };
+
+    Ok((number * multiplier).round() as i64)
+}
+
+/// 解析 size: 条件
+fn parse_size_filter(value: &str) -> Result<(String, Vec<Value>), String> {
+    if let Some((from, to)) = value.split_once("..") {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+        if !from.trim().is_empty() {
+            clauses.push("i.size >= ?");
+            params.push(Value::Integer(parse_size(from)?));
+        }
+        if !to.trim().is_empty() {
+            clauses.push("i.size <= ?");
+            params.push(Value::Integer(parse_size(to)?));
+        }
+        if clauses.is_empty() {
+            return Err(format!("无效的文件大小范围: {}", value));
+        }
+        return Ok((format!("({})", clauses.join(" AND ")), params));
+    }
+
+    let (cmp, rest) = split_comparison(value);
+    let size = parse_size(rest)?;
+    let op = match cmp {
+        Comparison::Gt => ">",
+        Comparison::Ge => ">=",
+        Comparison::Lt => "<",
+        Comparison::Le => "<=",
+        Comparison::Eq => "=",
+    };
+
+    Ok((format!("i.size {} ?", op), vec![Value::Integer(size)]))
+}
+
+/// 解析像素值，如 "1920"、"1920px"
+fn parse_pixels(text: &str) -> Result<i64, String> {
+    let text = text.trim();
+    text.trim_end_matches("px")
+        .trim()
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| format!("无效的像素值: {}", text))
+}
+
+/// 解析 width:、height: 条件
+fn parse_dimension_filter(column: &str, value: &str) -> Result<(String, Vec<Value>), String> {
+    if let Some((from, to)) = value.split_once("..") {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+        if !from.trim().is_empty() {
+            clauses.push(format!("{} >= ?", column));
+            params.push(Value::Integer(parse_pixels(from)?));
+        }
+        if !to.trim().is_empty() {
+            clauses.push(format!("{} <= ?", column));
+            params.push(Value::Integer(parse_pixels(to)?));
+        }
+        if clauses.is_empty() {
+            return Err(format!("无效的像素范围: {}", value));
+        }
+        return Ok((format!("({})", clauses.join(" AND ")), params));
+    }
+
+    let (cmp, rest) = split_comparison(value);
+    let pixels = parse_pixels(rest)?;
+    let op = match cmp {
+        Comparison::Gt => ">",
+        Comparison::Ge => ">=",
+        Comparison::Lt => "<",
+        Comparison::Le => "<=",
+        Comparison::Eq => "=",
+    };
+
+    Ok((format!("{} {} ?", column, op), vec![Value::Integer(pixels)]))
+}
+
+/// 解析最低分辨率，如 "1920x1080"（可带 ">=" 前缀）
+fn parse_resolution(value: &str) -> Result<(i64, i64), String> {
+    let text = value.trim().trim_start_matches(">=").to_lowercase();
+    let (width, height) = text
+        .split_once(['x', '*', '×'])
+        .ok_or_else(|| format!("无效的分辨率: {}", value))?;
+    Ok((parse_pixels(width)?, parse_pixels(height)?))
+}
+
+/// 解析日期或时间段，返回 [开始, 结束) 区间，支持 "2024"、"2024-01"、"2024-01-15"
+fn parse_date_period(text: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let invalid = || format!("无效的日期: {}", text);
+    let parts: Vec<&str> = text.trim().split(['-', '/']).collect();
+    let numbers: Vec<u32> = parts
+        .iter()
+        .map(|p| p.parse::<u32>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+
+    match numbers.as_slice() {
+        [year] => {
+            let year = *year as i32;
+            let start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(invalid)?;
+            let end = NaiveDate::from_ymd_opt(year + 1, 1, 1).ok_or_else(invalid)?;
+            Ok((start, end))
+        }
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, 1).ok_or_else(invalid)?;
+            let end = start.checked_add_months(Months::new(1)).ok_or_else(invalid)?;
+            Ok((start, end))
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, *day).ok_or_else(invalid)?;
+            let end = start.succ_opt().ok_or_else(invalid)?;
+            Ok((start, end))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// 解析 date: 条件，比较拍摄时间（没有 EXIF 拍摄时间时使用导入时间）
+fn parse_date_filter(value: &str) -> Result<(String, Vec<Value>), String> {
+    const DATE_EXPR: &str = "COALESCE(m.exif_datetime, i.created_at)";
+    let format_date = |d: NaiveDate| Value::Text(d.format("%Y-%m-%d 00:00:00").to_string());
+
+    let (start, end) = if let Some((from, to)) = value.split_once("..") {
+        let start = match from.trim() {
+            "" => None,
+            f => Some(parse_date_period(f)?.0),
+        };
+        let end = match to.trim() {
+            "" => None,
+            t => Some(parse_date_period(t)?.1),
+        };
+        (start, end)
+    } else {
+        let (cmp, rest) = split_comparison(value);
+        let (period_start, period_end) = parse_date_period(rest)?;
+        match cmp {
+            Comparison::Gt => (Some(period_end), None),
+            Comparison::Ge => (Some(period_start), None),
+            Comparison::Lt => (None, Some(period_start)),
+            Comparison::Le => (None, Some(period_end)),
+            Comparison::Eq => (Some(period_start), Some(period_end)),
+        }
+    };
+
+    let mut clauses = Vec::new();
+    let mut params = Vec::new();
+    if let Some(start) = start {
+        clauses.push(format!("{} >= ?", DATE_EXPR));
+        params.push(format_date(start));
+    }
+    if let Some(end) = end {
+        clauses.push(format!("{} < ?", DATE_EXPR));
+        params.push(format_date(end));
+    }
+    if clauses.is_empty() {
+        return Err(format!("无效的日期范围: {}", value));
+    }
+
+    Ok((format!("({})", clauses.join(" AND ")), params))
+}
+
+/// 转义为 FTS5 字符串（双引号包裹，内部双引号加倍）
+fn quote_fts_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// 是否包含可被分词器索引的字符（纯标点会产生空查询）
+fn has_searchable_chars(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+/// 是否为中日韩文字（汉字、假名、谚文）
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}'   // 汉字扩展 A
+        | '\u{4E00}'..='\u{9FFF}'   // 基本汉字
+        | '\u{AC00}'..='\u{D7AF}'   // 谚文音节
+        | '\u{F900}'..='\u{FAFF}'   // 兼容汉字
+        | '\u{20000}'..='\u{2A6DF}' // 汉字扩展 B
+    )
+}
+
+/// 中日韩文字切分为相互重叠的二元词，写入索引时使用
+///
+/// FTS5 默认的 unicode61 分词器会把连续的汉字当作一个词。切分后 "风景照" 变为
+/// "风景 景照 照"：每两个相邻的字成为一个词，末尾再补一个单字，使任意位置的单字都能以前缀匹配命中。
+/// 查询时使用 segment_cjk_query 以同样方式切分后作为短语匹配。
+pub fn segment_cjk(text: &str) -> String {
+    segment_bigrams(text, true)
+}
+
+/// 查询词的二元切分：与 segment_cjk 相同，但文本末尾的汉字串不补单字
+///
+/// 末尾的汉字串在文档中可能还有后续文字（"风景" 对应索引中的 "风景 景照 照"），
+/// 补上单字会要求短语中紧跟一个单字词而无法命中。
+fn segment_cjk_query(text: &str) -> String {
+    segment_bigrams(text, false)
+}
+
+fn segment_bigrams(text: &str, complete_last_run: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len() * 3);
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if !is_cjk(c) {
+            if i > 0 && is_cjk(chars[i - 1]) && !c.is_whitespace() {
+                result.push(' ');
+            }
+            result.push(c);
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && is_cjk(chars[i]) {
+            i += 1;
+        }
+        let run = &chars[start..i];
+
+        if start > 0 && !chars[start - 1].is_whitespace() {
+            result.push(' ');
+        }
+        let mut words: Vec<String> = run.windows(2).map(|pair| pair.iter().collect()).collect();
+        if run.len() == 1 || complete_last_run || i < chars.len() {
+            words.push(run[run.len() - 1].to_string());
+        }
+        result.push_str(&words.join(" "));
+    }
+
+    result
+}
+
+pub fn set_pinyin_enabled(enabled: bool) {
+    PINYIN_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn pinyin_enabled() -> bool {
+    PINYIN_ENABLED.load(Ordering::SeqCst)
+}
+
+/// 生成文本中汉字串的拼音索引词
+///
+/// 每个汉字串的每个后缀都生成全拼和首字母两个词，如 "风景照" 生成
+/// "fengjingzhao jingzhao zhao fjz jz z"，这样 "fengjing"、"jing"、"fj" 都能以前缀匹配命中。
+pub fn pinyin_tokens(text: &str) -> String {
+    let mut runs: Vec<Vec<&'static str>> = Vec::new();
+    let mut current: Vec<&'static str> = Vec::new();
+
+    for c in text.chars() {
+        match c.to_pinyin() {
+            Some(p) => current.push(p.plain()),
+            None => {
+                if !current.is_empty() {
+                    runs.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        runs.push(current);
+    }
+
+    let mut full = Vec::new();
+    let mut initials = Vec::new();
+
+    for run in &runs {
+        for start in 0..run.len() {
+            let end = run.len().min(start + MAX_PINYIN_RUN);
+            let syllables = &run[start..end];
+            full.push(syllables.concat());
+            initials.push(syllables.iter().filter_map(|s| s.chars().next()).collect::<String>());
+        }
+    }
+
+    full.extend(initials);
+    full.join(" ")
+}
+
+/// snippet() 中标记命中位置的字符（Unicode 私用区），HTML 转义后再替换为 <mark> 标签
+pub const SNIPPET_MARK_START: char = '\u{E000}';
+pub const SNIPPET_MARK_END: char = '\u{E001}';
+
+/// 把 snippet() 生成的摘要转成可直接显示的 HTML：还原二元切分的文字，
+/// 转义文件名、描述等原文中的 HTML 字符，命中部分用 <mark> 包围。没有命中时返回 None
+pub fn render_snippet(text: &str) -> Option<String> {
+    if !text.contains(SNIPPET_MARK_START) {
+        return None;
+    }
+
+    let mut html = String::with_capacity(text.len());
+    for c in compact_cjk_spacing(text).chars() {
+        match c {
+            SNIPPET_MARK_START => html.push_str("<mark>"),
+            SNIPPET_MARK_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    Some(html)
+}
+
+/// 还原摘要中被二元切分的文字：去掉插入的空格和重叠的字（跳过命中标记判断相邻字符）
+fn compact_cjk_spacing(text: &str) -> String {
+    let is_char = |c: &char| *c != SNIPPET_MARK_START && *c != SNIPPET_MARK_END;
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut result = String::with_capacity(text.len());
+    let mut skip_overlap = false;
+    for (index, &c) in chars.iter().enumerate() {
+        match c {
+            SNIPPET_MARK_START | SNIPPET_MARK_END => result.push(c),
+            ' ' => {
+                let prev = chars[..index].iter().rev().copied().find(is_char);
+                let next = chars[index + 1..].iter().copied().find(is_char);
+                match (prev, next) {
+                    // "风景 景照"：下一个词的首字与上一个词的末字重叠
+                    (Some(p), Some(n)) if is_cjk(p) && p == n => skip_overlap = true,
+                    (Some(p), Some(n))
+                        if !p.is_whitespace() && !n.is_whitespace() && (is_cjk(p) || is_cjk(n)) => {}
+                    _ => result.push(' '),
+                }
+            }
+            _ if skip_overlap => skip_overlap = false,
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_cjk_emits_overlapping_bigrams() {
+        assert_eq!(segment_cjk("风景照"), "风景 景照 照");
+        assert_eq!(segment_cjk("风"), "风");
+        assert_eq!(segment_cjk("夏天sunset风景"), "夏天 天 sunset 风景 景");
+        assert_eq!(segment_cjk("beach photo"), "beach photo");
+    }
+
+    #[test]
+    fn query_segmentation_matches_index_tokens() {
+        // 末尾汉字串不补单字，短语 "风景 景照" 是 "风景 景照 照片 片" 中的相邻词
+        assert_eq!(segment_cjk_query("风景照"), "风景 景照");
+        assert_eq!(segment_cjk_query("风景cd"), "风景 景 cd");
+        assert_eq!(segment_cjk_query("风"), "风");
+    }
+
+    #[test]
+    fn fts_term_prefix_matches_trailing_cjk() {
+        assert_eq!(fts_term("风景", false).as_deref(), Some("\"风景\"*"));
+        assert_eq!(fts_term("风景", true).as_deref(), Some("\"风景\"*"));
+        assert_eq!(fts_term("sunset beach", true).as_deref(), Some("\"sunset beach\""));
+        assert_eq!(fts_term("...", false), None);
+    }
+
+    #[test]
+    fn compact_cjk_spacing_restores_text() {
+        assert_eq!(compact_cjk_spacing("\u{E000}风景 景照\u{E001} 照片 片"), "\u{E000}风景照\u{E001}片");
+        assert_eq!(compact_cjk_spacing("天天 天天 天"), "天天天");
+        assert_eq!(compact_cjk_spacing("夏天 天 sunset 风景 景"), "夏天sunset风景");
+        assert_eq!(compact_cjk_spacing("beach photo"), "beach photo");
+    }
+
+    #[test]
+    fn render_snippet_escapes_text_and_marks_hits() {
+        assert_eq!(
+            render_snippet("\u{E000}风景 景照\u{E001} 照片 片").as_deref(),
+            Some("<mark>风景照</mark>片")
+        );
+        assert_eq!(
+            render_snippet("<img src=x onerror=\"a&b\"> \u{E000}cat\u{E001}.jpg").as_deref(),
+            Some("&lt;img src=x onerror=&quot;a&amp;b&quot;&gt; <mark>cat</mark>.jpg")
+        );
+        assert_eq!(render_snippet("<mark>cat</mark>"), None);
+    }
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    #[test]
+    fn plain_words_become_prefix_terms() {
+        let query = parse_query("sunset beach").unwrap();
+        assert_eq!(query.fts_query.as_deref(), Some("\"sunset\"* \"beach\"*"));
+        assert!(query.conditions.is_empty());
+        let blank = parse_query("  ").unwrap();
+        assert!(blank.fts_query.is_none() && blank.conditions.is_empty());
+    }
+
+    #[test]
+    fn quoted_phrases_match_exactly_and_escape_operators() {
+        let query = parse_query("\"sunset beach\" OR").unwrap();
+        assert_eq!(query.fts_query.as_deref(), Some("\"sunset beach\" \"OR\"*"));
+
+        // 缺少结束引号时读到末尾，FTS5 运算符和引号都被转义
+        let query = parse_query("\"a NEAR b").unwrap();
+        assert_eq!(query.fts_query.as_deref(), Some("\"a NEAR b\""));
+        assert_eq!(quote_fts_string("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn negated_words_exclude_matches() {
+        let query = parse_query("-cat dog").unwrap();
+        assert_eq!(query.fts_query.as_deref(), Some("\"dog\"*"));
+        assert_eq!(
+            query.conditions,
+            vec!["i.id NOT IN (SELECT rowid FROM images_fts WHERE images_fts MATCH ?)"]
+        );
+        assert_eq!(query.params, vec![text("\"cat\"*")]);
+    }
+
+    #[test]
+    fn field_filters_build_conditions() {
+        let query = parse_query("tag:travel -tag:\"old trip\" camera:\"Canon EOS\" name:IMG_").unwrap();
+        assert!(query.fts_query.is_none());
+        assert_eq!(query.conditions.len(), 4);
+        assert!(query.conditions[1].starts_with("NOT (EXISTS"));
+        assert_eq!(
+            query.params,
+            vec![text("travel"), text("old trip"), text("%Canon EOS%"), text("%IMG\\_%")]
+        );
+    }
+
+    #[test]
+    fn extension_filter_expands_aliases() {
+        let query = parse_query("ext:jpg,.PNG").unwrap();
+        assert_eq!(query.conditions, vec!["(lower(i.path) LIKE ? OR lower(i.path) LIKE ? OR lower(i.path) LIKE ?)"]);
+        assert_eq!(query.params, vec![text("%.jpg"), text("%.jpeg"), text("%.png")]);
+        assert!(parse_query("ext:,").is_err());
+    }
+
+    #[test]
+    fn size_filters_parse_units_and_ranges() {
+        let query = parse_query("size:>5MB").unwrap();
+        assert_eq!(query.conditions, vec!["i.size > ?"]);
+        assert_eq!(query.params, vec![Value::Integer(5 * 1024 * 1024)]);
+
+        let query = parse_query("size:500KB..1.5m").unwrap();
+        assert_eq!(query.conditions, vec!["(i.size >= ? AND i.size <= ?)"]);
+        assert_eq!(query.params, vec![Value::Integer(500 * 1024), Value::Integer(1572864)]);
+
+        assert!(parse_query("size:5TB").is_err());
+        assert!(parse_query("size:..").is_err());
+    }
+
+    #[test]
+    fn date_filters_cover_whole_periods() {
+        let query = parse_query("date:2024-02").unwrap();
+        assert_eq!(
+            query.params,
+            vec![text("2024-02-01 00:00:00"), text("2024-03-01 00:00:00")]
+        );
+
+        let query = parse_query("date:>2024").unwrap();
+        assert_eq!(query.params, vec![text("2025-01-01 00:00:00")]);
+
+        let query = parse_query("date:2024-01..2024-06").unwrap();
+        assert_eq!(
+            query.params,
+            vec![text("2024-01-01 00:00:00"), text("2024-07-01 00:00:00")]
+        );
+
+        assert!(parse_query("date:2024-13").is_err());
+    }
+
+    #[test]
+    fn dimension_filters_parse_pixels_and_orientation() {
+        let query = parse_query("width:>=1920px res:1280x720 orientation:竖图").unwrap();
+        assert_eq!(
+            query.conditions,
+            vec!["i.width >= ?", "(i.width >= ? AND i.height >= ?)", "i.aspect_ratio < ?"]
+        );
+        assert_eq!(
+            query.params[..3],
+            [Value::Integer(1920), Value::Integer(1280), Value::Integer(720)]
+        );
+        assert!(parse_query("orientation:diagonal").is_err());
+        assert!(parse_query("res:1920").is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_searched_as_text() {
+        let query = parse_query("12:30 foo:").unwrap();
+        assert_eq!(query.fts_query.as_deref(), Some("\"12:30\"*"));
+    }
+}
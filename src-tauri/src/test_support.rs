@@ -0,0 +1,87 @@
+//! 单元测试使用的临时图片库：配置文件、数据库和存储目录都放在独立的临时目录中
+
+use crate::config::{self, AppConfig};
+use crate::database;
+use crate::upload;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// 当前测试图片库的根目录，设置后配置文件和数据库路径都指向其中
+static TEST_ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 配置和数据库是全局的，使用测试图片库的测试依次执行
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+static TEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 测试图片库的根目录，没有测试图片库时返回 None
+pub fn root() -> Option<PathBuf> {
+    TEST_ROOT.lock().unwrap().clone()
+}
+
+/// 临时图片库，离开作用域时删除
+pub struct TestLibrary {
+    root: PathBuf,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestLibrary {
+    pub fn new() -> Self {
+        // 前一个测试失败时锁会被标记为中毒，不影响后续测试
+        let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let root = std::env::temp_dir().join(format!(
+            "images-manage-test-{}-{}",
+            std::process::id(),
+            TEST_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        *TEST_ROOT.lock().unwrap() = Some(root.clone());
+
+        let dir = |name: &str| Some(root.join(name).to_string_lossy().to_string());
+        let config = AppConfig {
+            images_dir: dir("images"),
+            thumbnails_dir: dir("thumbnails"),
+            renditions_dir: dir("renditions"),
+            ..AppConfig::default()
+        };
+        config::save_config(&config).unwrap();
+        database::init_database().unwrap();
+
+        Self { root, _guard: guard }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 在 source 目录中生成一张纯色 PNG 并导入，返回图片 ID（颜色不同则哈希不同）
+    pub fn import_png(&self, name: &str, color: [u8; 3]) -> i32 {
+        let path = self.write_png(name, color);
+        upload::upload_image_from_path(&path.to_string_lossy())
+            .unwrap()
+            .image_id
+            .expect("图片应为新导入")
+    }
+
+    /// 在 source 目录中生成一张纯色 PNG，返回文件路径
+    pub fn write_png(&self, name: &str, color: [u8; 3]) -> PathBuf {
+        let dir = self.root.join("source");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        image::RgbImage::from_pixel(16, 12, image::Rgb(color))
+            .save(&path)
+            .unwrap();
+        path
+    }
+}
+
+impl Drop for TestLibrary {
+    fn drop(&mut self) {
+        *TEST_ROOT.lock().unwrap() = None;
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
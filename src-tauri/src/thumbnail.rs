@@ -0,0 +1,126 @@
+use crate::config;
+use crate::database::{self, ImageRecord};
+use crate::image::{encode_image, open_image, OutputFormat};
+use crate::upload::write_file_atomic;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 缩略图 JPEG 编码质量
+const THUMBNAIL_JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThumbnailFailure {
+    pub image_id: i32,
+    pub filename: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThumbnailReport {
+    /// 缺少缩略图的图片数量
+    pub total: usize,
+    /// 成功生成的数量
+    pub generated: usize,
+    pub failed: Vec<ThumbnailFailure>,
+}
+
+/// 根据哈希值生成缩略图存储路径（与原图一样按哈希前两位分目录）
+pub fn get_thumbnail_storage_path(hash: &str, extension: &str) -> PathBuf {
+    let mut path = config::get_thumbnails_dir();
+    let prefix = &hash[..hash.len().min(2)];
+    path.push(prefix);
+    path.push(format!("{}{}", hash, extension));
+    path
+}
+
+/// 按配置的最大宽高生成缩略图文件，返回缩略图路径
+///
+/// 不透明图片输出 JPEG，带透明通道的输出 PNG；小于限制的图片不放大。
+pub fn generate_thumbnail(source: &Path, hash: &str) -> Result<PathBuf, String> {
+    let config = config::load_config();
+    let max_width = config.thumbnail_max_width.max(1);
+    let max_height = config.thumbnail_max_height.max(1);
+
+    let img = open_image(source)?;
+    let thumb = if img.width() > max_width || img.height() > max_height {
+        img.thumbnail(max_width, max_height)
+    } else {
+        img
+    };
+
+    let format = if thumb.color().has_alpha() {
+        OutputFormat::Png
+    } else {
+        OutputFormat::Jpeg
+    };
+    let data = encode_image(&thumb, format, THUMBNAIL_JPEG_QUALITY)?;
+
+    let thumb_path = get_thumbnail_storage_path(hash, format.extension());
+    write_file_atomic(&thumb_path, &data)?;
+
+    Ok(thumb_path)
+}
+
+/// 为已入库的图片生成缩略图并记录到数据库
+pub fn create_thumbnail(image_id: i32, source: &Path, hash: &str) -> Result<String, String> {
+    let thumb_path = generate_thumbnail(source, hash)?;
+
+    let thumb_path_str = thumb_path
+        .to_str()
+        .ok_or_else(|| "缩略图路径编码错误".to_string())?
+        .to_string();
+
+    database::update_thumbnail_path(image_id, Some(&thumb_path_str))
+        .map_err(|e| format!("保存缩略图路径失败: {}", e))?;
+
+    Ok(thumb_path_str)
+}
+
+/// 缩略图是否缺失（未记录或文件已不存在）
+pub fn is_thumbnail_missing(thumbnail_path: Option<&str>) -> bool {
+    match thumbnail_path {
+        Some(path) => !Path::new(path).exists(),
+        None => true,
+    }
+}
+
+/// 返回图片的缩略图路径，缺失时即时生成
+pub fn ensure_thumbnail(record: &ImageRecord) -> Result<PathBuf, String> {
+    if let Some(path) = &record.thumbnail_path {
+        if Path::new(path).exists() {
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    create_thumbnail(record.id, Path::new(&record.path), &record.hash).map(PathBuf::from)
+}
+
+/// 为所有缺少缩略图的图片补生成缩略图
+pub fn generate_missing_thumbnails() -> Result<ThumbnailReport, String> {
+    let records = database::get_all_images()
+        .map_err(|e| format!("查询图片失败: {}", e))?;
+
+    let missing: Vec<_> = records
+        .into_iter()
+        .filter(|record| is_thumbnail_missing(record.thumbnail_path.as_deref()))
+        .collect();
+
+    let mut report = ThumbnailReport {
+        total: missing.len(),
+        generated: 0,
+        failed: Vec::new(),
+    };
+
+    for record in missing {
+        match create_thumbnail(record.id, Path::new(&record.path), &record.hash) {
+            Ok(_) => report.generated += 1,
+            Err(error) => report.failed.push(ThumbnailFailure {
+                image_id: record.id,
+                filename: record.filename,
+                error,
+            }),
+        }
+    }
+
+    Ok(report)
+}
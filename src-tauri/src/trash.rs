@@ -0,0 +1,323 @@
+use crate::config;
+use crate::database::{self, ImageRecord};
+use crate::raw;
+use crate::rendition;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// 自动清理过期回收站图片的间隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+static AUTO_PURGE_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// 获取回收站目录
+pub fn get_trash_dir() -> PathBuf {
+    let mut path = config::get_images_dir();
+    path.push(".trash");
+    path
+}
+
+/// 移动文件，不能直接重命名（如跨磁盘）时复制后删除
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| format!("移动文件失败: {}", e))?;
+    fs::remove_file(from).map_err(|e| format!("删除原文件失败: {}", e))
+}
+
+/// 与图片一起删除、恢复的图片：JPEG 主图连同配对的 RAW
+fn with_paired(record: ImageRecord) -> Result<Vec<ImageRecord>, String> {
+    let is_raw = record.mime_type.as_deref().is_some_and(raw::is_raw_mime);
+    let paired_id = record.paired_id;
+
+    let mut records = vec![record];
+    if let (false, Some(paired_id)) = (is_raw, paired_id) {
+        let paired = database::get_image_by_id(paired_id)
+            .map_err(|e| format!("查询配对图片失败: {}", e))?;
+        records.push(paired);
+    }
+    Ok(records)
+}
+
+fn trash_record(record: &ImageRecord) -> Result<(), String> {
+    let source = Path::new(&record.path);
+    let target = get_trash_dir().join(source.file_name().unwrap_or_default());
+
+    let file_exists = source.exists();
+    if file_exists {
+        move_file(source, &target)?;
+    }
+
+    let target_str = target.to_string_lossy().to_string();
+    if let Err(e) = database::mark_image_trashed(record.id, &target_str) {
+        if file_exists {
+            let _ = move_file(&target, source);
+        }
+        return Err(format!("更新数据库失败: {}", e));
+    }
+    Ok(())
+}
+
+/// 将图片移入回收站：标记数据库记录并把文件移到回收站目录
+///
+/// JPEG 与 RAW 配对时一起移入。缩略图保留，以便在回收站中预览。
+pub fn trash_image(id: i32) -> Result<(), String> {
+    let record = database::get_image_by_id(id).map_err(|e| format!("图片不存在: {}", e))?;
+    if record.deleted_at.is_some() {
+        return Err("图片已在回收站中".to_string());
+    }
+
+    for record in with_paired(record)? {
+        if record.deleted_at.is_none() {
+            trash_record(&record)?;
+        }
+    }
+    Ok(())
+}
+
+fn restore_record(record: &ImageRecord) -> Result<(), String> {
+    let source = Path::new(&record.path);
+    let extension = source
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let target = database::get_image_storage_path(&record.hash, &extension);
+
+    if !source.exists() {
+        return Err(format!("回收站中的文件不存在: {}", record.path));
+    }
+    move_file(source, &target)?;
+
+    let target_str = target.to_string_lossy().to_string();
+    if let Err(e) = database::mark_image_restored(record.id, &target_str) {
+        let _ = move_file(&target, source);
+        return Err(format!("更新数据库失败: {}", e));
+    }
+    Ok(())
+}
+
+/// 从回收站恢复图片（连同一起删除的配对 RAW）
+pub fn restore_image(id: i32) -> Result<(), String> {
+    let record = database::get_image_by_id(id).map_err(|e| format!("图片不存在: {}", e))?;
+    if record.deleted_at.is_none() {
+        return Err("图片不在回收站中".to_string());
+    }
+
+    for record in with_paired(record)? {
+        if record.deleted_at.is_some() {
+            restore_record(&record)?;
+        }
+    }
+    Ok(())
+}
+
+/// 再次导入回收站中的图片时恢复原记录
+///
+/// 回收站中的文件已丢失时，先把导入的文件放回回收站位置再恢复。
+pub fn restore_reimported(record: &ImageRecord, file_path: &Path) -> Result<(), String> {
+    let trashed = Path::new(&record.path);
+    if !trashed.exists() {
+        if let Some(parent) = trashed.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        fs::copy(file_path, trashed).map_err(|e| format!("复制图片文件失败: {}", e))?;
+    }
+    restore_image(record.id)
+}
+
+/// 恢复失败的图片
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreFailure {
+    pub id: i32,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// 成功恢复的图片数量
+    pub restored: usize,
+    pub failed: Vec<RestoreFailure>,
+}
+
+/// 逐个恢复图片，某张失败时继续恢复其余图片
+pub fn restore_images(ids: &[i32]) -> RestoreReport {
+    let mut report = RestoreReport::default();
+    for &id in ids {
+        match restore_image(id) {
+            Ok(()) => report.restored += 1,
+            Err(error) => report.failed.push(RestoreFailure { id, error }),
+        }
+    }
+    report
+}
+
+/// 列出回收站中的图片，最近删除的在前
+pub fn list_trash() -> Result<Vec<ImageRecord>, String> {
+    database::get_trashed_images(None).map_err(|e| format!("查询回收站失败: {}", e))
+}
+
+/// 永久删除图片：文件、数据库记录、缩略图和缓存的缩放图
+///
+/// 先删除文件，失败时保留数据库记录，以免留下无记录的文件；文件已不存在时直接删除记录。
+fn purge_record(record: &ImageRecord) -> Result<(), String> {
+    match fs::remove_file(&record.path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("删除文件失败: {}", e)),
+    }
+    database::delete_image(record.id).map_err(|e| format!("删除数据库记录失败: {}", e))?;
+
+    if let Some(thumbnail_path) = &record.thumbnail_path {
+        let _ = fs::remove_file(thumbnail_path);
+    }
+    rendition::remove_renditions(&record.hash);
+    Ok(())
+}
+
+fn purge_records(records: Vec<ImageRecord>) -> Result<usize, String> {
+    let mut purged = 0;
+    for record in records {
+        match purge_record(&record) {
+            Ok(()) => purged += 1,
+            Err(e) => eprintln!("永久删除图片失败 ({}): {}", record.filename, e),
+        }
+    }
+    Ok(purged)
+}
+
+/// 清空回收站，返回永久删除的图片数量
+pub fn empty_trash() -> Result<usize, String> {
+    purge_records(list_trash()?)
+}
+
+/// 永久删除超过保留天数的回收站图片
+pub fn purge_expired() -> Result<usize, String> {
+    let retention_days = config::load_config().trash_retention_days;
+    if retention_days == 0 {
+        return Ok(0);
+    }
+
+    let records = database::get_trashed_images(Some(retention_days))
+        .map_err(|e| format!("查询回收站失败: {}", e))?;
+    purge_records(records)
+}
+
+/// 启动后台线程定期清理过期的回收站图片（只启动一次）
+pub fn start_auto_purge() {
+    if AUTO_PURGE_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| loop {
+        match purge_expired() {
+            Ok(0) => {}
+            Ok(purged) => println!("已自动清理回收站中的 {} 张图片", purged),
+            Err(e) => eprintln!("自动清理回收站失败: {}", e),
+        }
+        thread::sleep(PURGE_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+    use crate::upload;
+
+    #[test]
+    fn trash_and_restore_move_the_file() {
+        let library = TestLibrary::new();
+        let id = library.import_png("a.png", [200, 30, 30]);
+        let stored = database::get_image_by_id(id).unwrap().path;
+
+        trash_image(id).unwrap();
+        let trashed = database::get_image_by_id(id).unwrap();
+        assert!(trashed.deleted_at.is_some());
+        assert!(Path::new(&trashed.path).starts_with(get_trash_dir()));
+        assert!(Path::new(&trashed.path).exists());
+        assert!(!Path::new(&stored).exists());
+        assert!(trash_image(id).is_err());
+
+        restore_image(id).unwrap();
+        let restored = database::get_image_by_id(id).unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(restored.path, stored);
+        assert!(Path::new(&stored).exists());
+        assert!(restore_image(id).is_err());
+    }
+
+    #[test]
+    fn reimporting_a_trashed_image_restores_it() {
+        let library = TestLibrary::new();
+        let id = library.import_png("a.png", [30, 200, 30]);
+        trash_image(id).unwrap();
+
+        let source = library.root().join("source").join("a.png");
+        let result = upload::upload_image_from_path(&source.to_string_lossy()).unwrap();
+        assert_eq!(result.image_id, Some(id));
+
+        let record = database::get_image_by_id(id).unwrap();
+        assert!(record.deleted_at.is_none());
+        assert!(Path::new(&record.path).exists());
+        assert!(list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reimport_uses_the_new_file_when_the_trashed_one_is_gone() {
+        let library = TestLibrary::new();
+        let id = library.import_png("a.png", [30, 30, 200]);
+        trash_image(id).unwrap();
+        fs::remove_file(database::get_image_by_id(id).unwrap().path).unwrap();
+
+        let source = library.root().join("source").join("a.png");
+        let result = upload::upload_image_from_path(&source.to_string_lossy()).unwrap();
+        assert_eq!(result.image_id, Some(id));
+
+        let record = database::get_image_by_id(id).unwrap();
+        assert!(record.deleted_at.is_none());
+        assert_eq!(fs::read(&record.path).unwrap(), fs::read(&source).unwrap());
+    }
+
+    #[test]
+    fn purge_removes_the_file_and_the_record() {
+        let library = TestLibrary::new();
+        let kept = library.import_png("kept.png", [10, 10, 10]);
+        let id = library.import_png("a.png", [200, 200, 30]);
+        // 文件已丢失的记录也能清理
+        let missing = library.import_png("missing.png", [30, 200, 200]);
+        trash_image(id).unwrap();
+        trash_image(missing).unwrap();
+        let trashed = database::get_image_by_id(id).unwrap().path;
+        fs::remove_file(database::get_image_by_id(missing).unwrap().path).unwrap();
+
+        assert_eq!(empty_trash().unwrap(), 2);
+        assert!(database::get_image_by_id(id).is_err());
+        assert!(database::get_image_by_id(missing).is_err());
+        assert!(!Path::new(&trashed).exists());
+        assert!(database::get_image_by_id(kept).is_ok());
+    }
+
+    #[test]
+    fn purge_keeps_the_record_when_the_file_cannot_be_deleted() {
+        let library = TestLibrary::new();
+        let id = library.import_png("a.png", [200, 30, 200]);
+        trash_image(id).unwrap();
+
+        // 用同名目录代替文件，使删除文件失败
+        let trashed = database::get_image_by_id(id).unwrap().path;
+        fs::remove_file(&trashed).unwrap();
+        fs::create_dir(&trashed).unwrap();
+
+        assert_eq!(empty_trash().unwrap(), 0);
+        assert!(database::get_image_by_id(id).unwrap().deleted_at.is_some());
+    }
+}
@@ -1,8 +1,10 @@
 use std::fs;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use sha2::{Digest, Sha256};
 use hex;
+use crate::image::SourceFormat;
 
 #[derive(Debug)]
 pub struct UploadResult {
@@ -34,11 +36,41 @@ pub fn calculate_file_hash(path: &Path) -> Result<String, String> {
     Ok(hex::encode(hasher.finalize()))
 }
 
+/// 临时文件计数，同一进程内并发写入的临时文件名不重复
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 与目标文件同目录的临时文件路径：完整文件名加进程号和计数，
+/// 同名不同扩展名的文件（如 .jpg 和 .png 缩略图）不会共用临时文件
+pub fn unique_temp_path(path: &Path) -> PathBuf {
+    let count = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", std::process::id(), count));
+    path.with_file_name(name)
+}
+
+/// 写入文件：先写临时文件再重命名，避免留下写了一半的文件
+pub fn write_file_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+    }
+
+    let tmp_path = unique_temp_path(path);
+    fs::write(&tmp_path, data)
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("写入文件失败: {}", e));
+    }
+
+    Ok(())
+}
+
 /// 上传图片文件
 pub fn upload_image_from_path(path: &str) -> Result<UploadResult, String> {
-    use crate::database;
-    use crate::image::validate_image_format;
-
     let file_path = Path::new(path);
 
     // 验证文件存在
@@ -46,39 +78,88 @@ pub fn upload_image_from_path(path: &str) -> Result<UploadResult, String> {
         return Err(format!("文件不存在: {}", path));
     }
 
-    // 获取文件元数据
-    let metadata = match fs::metadata(&file_path) {
-        Ok(meta) => meta,
-        Err(e) => return Err(format!("无法读取文件元数据: {}", e)),
-    };
-
-    let file_size = metadata.len() as i64;
-
-    // 获取文件名和扩展名
+    // 获取文件名
     let filename = match file_path.file_name() {
         Some(name) => name.to_string_lossy().to_string(),
         None => return Err("无法获取文件名".to_string()),
     };
 
-    let extension = match file_path.extension() {
-        Some(ext) => format!(".{}", ext.to_string_lossy()),
-        None => return Err("无法获取文件扩展名".to_string()),
-    };
+    import_image_file(file_path, &filename)
+}
 
-    // 验证图片格式
-    if let Err(e) = validate_image_format(path) {
-        return Err(format!("图片格式验证失败: {}", e));
-    }
+/// 获取上传临时文件目录
+pub fn get_upload_temp_dir() -> PathBuf {
+    let mut path = crate::config::get_images_dir();
+    path.push(".uploads");
+    path
+}
 
+/// 导入图片文件：哈希去重后复制到存储目录并写入数据库
+///
+/// file_path 为实际读取的文件（可以是上传的临时文件），filename 为原始文件名，
+/// 用于显示和判断图片格式。
+pub fn import_image_file(file_path: &Path, filename: &str) -> Result<UploadResult, String> {
     // 计算文件哈希
     let hash = match calculate_file_hash(file_path) {
         Ok(h) => h,
         Err(e) => return Err(format!("计算文件哈希失败: {}", e)),
     };
 
+    import_hashed_image_file(file_path, filename, &hash)
+}
+
+/// 导入已计算好哈希的图片文件，供批量导入时复用并行计算的哈希
+pub fn import_hashed_image_file(file_path: &Path, filename: &str, hash: &str) -> Result<UploadResult, String> {
+    import_file(file_path, filename, hash, None)
+}
+
+/// 导入已通过 validate_image_file 校验的图片文件，返回的错误都与文件内容无关（可以重试）
+pub fn import_validated_image_file(
+    file_path: &Path,
+    filename: &str,
+    format: SourceFormat,
+) -> Result<UploadResult, String> {
+    let hash = calculate_file_hash(file_path)
+        .map_err(|e| format!("计算文件哈希失败: {}", e))?;
+    import_file(file_path, filename, &hash, Some(format))
+}
+
+/// format 为 None 时先按内容校验图片格式
+fn import_file(
+    file_path: &Path,
+    filename: &str,
+    hash: &str,
+    format: Option<SourceFormat>,
+) -> Result<UploadResult, String> {
+    use crate::config;
+    use crate::database;
+    use crate::image::validate_image_file;
+    use crate::metadata;
+    use crate::raw;
+    use crate::thumbnail;
+    use crate::trash;
+
+    // 获取文件元数据
+    let metadata = match fs::metadata(file_path) {
+        Ok(meta) => meta,
+        Err(e) => return Err(format!("无法读取文件元数据: {}", e)),
+    };
+
+    let file_size = metadata.len() as i64;
+
     // 检查图片是否已存在
-    match database::image_exists_by_hash(&hash) {
-        Ok(true) => {
+    match database::get_image_by_hash(hash) {
+        // 回收站中的图片再次导入时恢复原记录，否则到期后会连同文件被清理
+        Ok(Some(existing)) if existing.deleted_at.is_some() => {
+            trash::restore_reimported(&existing, file_path)?;
+            return Ok(UploadResult {
+                success: true,
+                message: format!("文件 '{}' 在回收站中，已恢复", filename),
+                image_id: Some(existing.id),
+                file_size: Some(file_size),
+            });
+        }
+        Ok(Some(_)) => {
             return Ok(UploadResult {
                 success: true,
                 message: format!("文件 '{}' 已存在，跳过上传", filename),
@@ -86,15 +167,22 @@ pub fn upload_image_from_path(path: &str) -> Result<UploadResult, String> {
                 file_size: Some(file_size),
             });
         }
-        Ok(false) => {}
+        Ok(None) => {}
         Err(e) => return Err(format!("检查图片是否存在失败: {}", e)),
     }
 
+    // 按文件内容识别格式，拒绝非图片和损坏的文件（已存在的图片无需重复校验）
+    let format = match format {
+        Some(format) => format,
+        None => validate_image_file(file_path)
+            .map_err(|e| format!("图片格式验证失败: {}", e))?,
+    };
+
     // 确保图片存储目录存在
     database::ensure_images_dir()?;
 
     // 获取目标存储路径
-    let storage_path = database::get_image_storage_path(&hash, &extension);
+    let storage_path = database::get_image_storage_path(hash, format.extension());
 
     // 确保目标目录存在（使用哈希前缀作为子目录）
     if let Some(parent) = storage_path.parent() {
@@ -105,7 +193,7 @@ pub fn upload_image_from_path(path: &str) -> Result<UploadResult, String> {
     }
 
     // 复制图片文件到存储目录
-    fs::copy(&file_path, &storage_path)
+    fs::copy(file_path, &storage_path)
         .map_err(|e| format!("复制图片文件失败: {}", e))?;
 
     // 将存储路径转换为字符串（用于数据库）
@@ -114,15 +202,47 @@ pub fn upload_image_from_path(path: &str) -> Result<UploadResult, String> {
         .ok_or_else(|| "存储路径编码错误".to_string())?;
 
     // 插入数据库（使用存储路径而不是原始路径）
-    let image_id = match database::insert_image(&filename, storage_path_str, file_size, &hash) {
+    let image_id = match database::insert_image(filename, storage_path_str, file_size, hash, format.mime_type()) {
         Ok(id) => id,
         Err(e) => {
+            // 同一文件被同时导入时，另一方已写入数据库，文件归其所有，不能删除
+            if let Ok(Some(_)) = database::get_image_by_hash(hash) {
+                return Ok(UploadResult {
+                    success: true,
+                    message: format!("文件 '{}' 已存在，跳过上传", filename),
+                    image_id: None,
+                    file_size: Some(file_size),
+                });
+            }
+
             // 如果数据库插入失败，删除已复制的文件
             let _ = fs::remove_file(&storage_path);
             return Err(format!("保存图片信息到数据库失败: {}", e));
         }
     };
 
+    // 提取 EXIF 信息（失败不影响上传结果）
+    if let Err(e) = metadata::extract_and_store(image_id, &storage_path) {
+        eprintln!("提取 EXIF 信息失败 ({}): {}", filename, e);
+    }
+
+    // 记录图片尺寸（失败不影响上传结果）
+    if let Err(e) = metadata::store_dimensions(image_id, &storage_path) {
+        eprintln!("读取图片尺寸失败 ({}): {}", filename, e);
+    }
+
+    // RAW 与同名 JPEG 配对（需在提取 EXIF 之后，用拍摄时间排除误配）
+    if let Err(e) = raw::link_pair(image_id) {
+        eprintln!("配对 RAW 与 JPEG 失败 ({}): {}", filename, e);
+    }
+
+    // 生成缩略图（失败不影响上传结果）
+    if config::load_config().auto_generate_thumbnails {
+        if let Err(e) = thumbnail::create_thumbnail(image_id, &storage_path, hash) {
+            eprintln!("生成缩略图失败 ({}): {}", filename, e);
+        }
+    }
+
     Ok(UploadResult {
         success: true,
         message: format!("文件 '{}' 上传成功", filename),
@@ -0,0 +1,177 @@
+use crate::config;
+use crate::database;
+use crate::image::validate_image_format;
+use crate::upload;
+use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// 文件最后一次变化后等待的时间，避免导入还没写完的文件
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// 检查待导入文件的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+static WATCHER: Mutex<Option<WatcherHandle>> = Mutex::new(None);
+
+enum WatchMessage {
+    Changed(Vec<PathBuf>),
+    Stop,
+}
+
+struct WatcherHandle {
+    // 释放时停止监视
+    _watcher: RecommendedWatcher,
+    tx: Sender<WatchMessage>,
+}
+
+/// 按配置启动（或重启）文件夹监视，并在后台补扫监视期间之外新增的文件
+pub fn restart() -> Result<(), String> {
+    stop();
+
+    let folders: Vec<PathBuf> = config::load_config()
+        .watched_folders
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    if folders.is_empty() {
+        return Ok(());
+    }
+
+    let (tx, rx) = mpsc::channel();
+
+    let event_tx = tx.clone();
+    let mut watcher = recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) => {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                let _ = event_tx.send(WatchMessage::Changed(event.paths));
+            }
+        }
+        Err(e) => eprintln!("监视文件夹出错: {}", e),
+    })
+    .map_err(|e| format!("创建文件夹监视失败: {}", e))?;
+
+    for folder in &folders {
+        if let Err(e) = watcher.watch(folder, RecursiveMode::Recursive) {
+            eprintln!("监视文件夹失败 ({}): {}", folder.display(), e);
+        }
+    }
+
+    thread::spawn(move || {
+        for folder in &folders {
+            scan_folder(folder);
+        }
+        run_debounce_loop(rx);
+    });
+
+    *WATCHER.lock().unwrap() = Some(WatcherHandle {
+        _watcher: watcher,
+        tx,
+    });
+
+    Ok(())
+}
+
+/// 停止文件夹监视
+pub fn stop() {
+    if let Some(handle) = WATCHER.lock().unwrap().take() {
+        let _ = handle.tx.send(WatchMessage::Stop);
+    }
+}
+
+/// 收集变化的文件，文件在 DEBOUNCE 时间内没有再变化后才导入
+fn run_debounce_loop(rx: mpsc::Receiver<WatchMessage>) {
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(WatchMessage::Changed(paths)) => {
+                for path in paths {
+                    pending.insert(path, Instant::now());
+                }
+            }
+            Ok(WatchMessage::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        let ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed_at)| changed_at.elapsed() >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in ready {
+            pending.remove(&path);
+            if path.is_dir() {
+                // 整个文件夹被移入时只会收到文件夹本身的事件
+                scan_folder(&path);
+            } else {
+                import_file(&path);
+            }
+        }
+    }
+}
+
+/// 补扫文件夹中尚未处理的图片
+fn scan_folder(folder: &Path) {
+    for entry in WalkDir::new(folder).into_iter().filter_map(Result::ok) {
+        if entry.file_type().is_file() {
+            import_file(entry.path());
+        }
+    }
+}
+
+/// 导入监视文件夹中的单个文件，大小和修改时间未变的已处理文件直接跳过
+fn import_file(path: &Path) {
+    if !matches!(validate_image_format(&path.to_string_lossy()), Ok(true)) {
+        return;
+    }
+
+    // 图库目录在监视文件夹内时不导入图库自身的文件
+    if path.starts_with(config::get_images_dir()) {
+        return;
+    }
+
+    let Ok(meta) = fs::metadata(path) else {
+        return;
+    };
+    if !meta.is_file() {
+        return;
+    }
+
+    let size = meta.len() as i64;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let path_str = path.to_string_lossy().to_string();
+
+    match database::is_watched_file_processed(&path_str, size, modified) {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("查询监视文件记录失败 ({}): {}", path_str, e);
+            return;
+        }
+    }
+
+    match upload::upload_image_from_path(&path_str) {
+        Ok(result) => {
+            if result.image_id.is_some() {
+                println!("自动导入: {}", path_str);
+            }
+            if let Err(e) = database::record_watched_file(&path_str, size, modified) {
+                eprintln!("保存监视文件记录失败 ({}): {}", path_str, e);
+            }
+        }
+        Err(e) => eprintln!("自动导入失败 ({}): {}", path_str, e),
+    }
+}
@@ -1,5 +1,6 @@
-import { BrowserRouter, Routes, Route, Navigate } from 'react-router-dom'
+import { BrowserRouter, Routes, Route, Navigate, useLocation } from 'react-router-dom'
 import { lazy, Suspense, useEffect, useState } from 'react'
+import { useAuthStore } from './stores/authStore'
 
 // 加载中组件
 function LoadingFallback() {
@@ -20,16 +21,31 @@ const MobileHome = lazy(() => import('./pages/MobileHome').then(m => ({ default:
 const MobileGallery = lazy(() => import('./pages/MobileGallery').then(m => ({ default: m.MobileGallery })))
 const MobileUpload = lazy(() => import('./pages/MobileUpload').then(m => ({ default: m.MobileUpload })))
 const MobileSettings = lazy(() => import('./pages/MobileSettings').then(m => ({ default: m.MobileSettings })))
+const MobileLogin = lazy(() => import('./pages/MobileLogin').then(m => ({ default: m.MobileLogin })))
+const MobilePair = lazy(() => import('./pages/MobilePair').then(m => ({ default: m.MobilePair })))
+
+// 未登录时跳转到登录页，登录后回到原页面
+function RequireAuth({ children }: { children: React.ReactElement }) {
+  const isAuthenticated = useAuthStore((state) => state.isAuthenticated)
+  const location = useLocation()
+
+  if (!isAuthenticated) {
+    return <Navigate to="/login" replace state={{ from: location.pathname }} />
+  }
+  return children
+}
 
 function WebApp() {
   return (
     <Suspense fallback={<LoadingFallback />}>
       <BrowserRouter>
         <Routes>
-          <Route path="/" element={<MobileHome />} />
-          <Route path="/gallery" element={<MobileGallery />} />
-          <Route path="/upload" element={<MobileUpload />} />
-          <Route path="/settings" element={<MobileSettings />} />
+          <Route path="/login" element={<MobileLogin />} />
+          <Route path="/pair" element={<MobilePair />} />
+          <Route path="/" element={<RequireAuth><MobileHome /></RequireAuth>} />
+          <Route path="/gallery" element={<RequireAuth><MobileGallery /></RequireAuth>} />
+          <Route path="/upload" element={<RequireAuth><MobileUpload /></RequireAuth>} />
+          <Route path="/settings" element={<RequireAuth><MobileSettings /></RequireAuth>} />
           <Route path="*" element={<Navigate to="/" replace />} />
         </Routes>
       </BrowserRouter>
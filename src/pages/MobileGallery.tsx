@@ -1,6 +1,7 @@
 import { useState, useEffect, useMemo } from 'react'
 import { useNavigate, useLocation } from 'react-router-dom'
 import { ImageViewer } from '../components/mobile/ImageViewer'
+import { apiFetch, mediaUrl, useMediaToken } from '@/utils/api'
 
 interface Image {
   id: number
@@ -14,7 +15,7 @@ interface Image {
 export function MobileGallery() {
   const navigate = useNavigate()
   const location = useLocation()
-  const [serverUrl] = useState(() => window.location.origin)
+  const mediaToken = useMediaToken()
   const [images, setImages] = useState<Image[]>([])
   const [loading, setLoading] = useState(true)
   const [searchQuery, setSearchQuery] = useState('')
@@ -63,7 +64,8 @@ export function MobileGallery() {
   const loadImages = async (query?: string, append = false) => {
     try {
       const searchParams = query ? `?search=${encodeURIComponent(query)}` : ''
-      const response = await fetch(`${serverUrl}/api/images${searchParams}`)
+      const response = await apiFetch(`/api/images${searchParams}`)
+      if (!response.ok) throw new Error(`HTTP ${response.status}`)
       const data = await response.json()
 
       if (append) {
@@ -95,9 +97,10 @@ export function MobileGallery() {
     if (!confirm('确定要删除这张图片吗？')) return
 
     try {
-      await fetch(`${serverUrl}/api/images/${id}`, {
+      const response = await apiFetch(`/api/images/${id}`, {
         method: 'DELETE',
       })
+      if (!response.ok) throw new Error('删除失败')
       setImages(prev => prev.filter(img => img.id !== id))
       setSelectedImage(null)
     } catch (error) {
@@ -110,7 +113,7 @@ export function MobileGallery() {
 
     setSavingDescription(true)
     try {
-      const response = await fetch(`${serverUrl}/api/images/${selectedImage.id}`, {
+      const response = await apiFetch(`/api/images/${selectedImage.id}`, {
         method: 'PUT',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ description: newDescription }),
@@ -151,7 +154,7 @@ export function MobileGallery() {
     setSavingFilename(true)
     setFilenameError('')
     try {
-      const response = await fetch(`${serverUrl}/api/images/${selectedImage.id}`, {
+      const response = await apiFetch(`/api/images/${selectedImage.id}`, {
         method: 'PUT',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ filename: newFilename }),
@@ -265,7 +268,7 @@ export function MobileGallery() {
                 }}
               >
                 <img
-                  src={`${serverUrl}/api/images/${image.id}/thumbnail`}
+                  src={mediaUrl(image.id, 'thumbnail', mediaToken)}
                   alt={image.filename}
                   loading="lazy"
                   className="w-full h-full object-cover"
@@ -314,7 +317,7 @@ export function MobileGallery() {
               {/* 预加载相邻图片 */}
               {currentIndex > 0 && (
                 <img
-                  src={`${serverUrl}/api/images/${images[currentIndex - 1].id}/file`}
+                  src={mediaUrl(images[currentIndex - 1].id, 'file', mediaToken)}
                   alt=""
                   className="hidden"
                   loading="eager"
@@ -322,7 +325,7 @@ export function MobileGallery() {
               )}
               {currentIndex < images.length - 1 && (
                 <img
-                  src={`${serverUrl}/api/images/${images[currentIndex + 1].id}/file`}
+                  src={mediaUrl(images[currentIndex + 1].id, 'file', mediaToken)}
                   alt=""
                   className="hidden"
                   loading="eager"
@@ -330,7 +333,7 @@ export function MobileGallery() {
               )}
 
               <ImageViewer
-                src={`${serverUrl}/api/images/${selectedImage.id}/file`}
+                src={mediaUrl(selectedImage.id, 'file', mediaToken) ?? ''}
                 alt={selectedImage.filename}
                 currentIndex={currentIndex}
                 totalImages={images.length}
@@ -1,5 +1,6 @@
 import { useState, useEffect } from 'react'
 import { useNavigate } from 'react-router-dom'
+import { apiFetch } from '@/utils/api'
 
 interface Stats {
   totalImages: number
@@ -18,8 +19,8 @@ export function MobileHome() {
 
   const loadStats = async () => {
     try {
-      const serverUrl = window.location.origin
-      const response = await fetch(`${serverUrl}/api/images`)
+      const response = await apiFetch('/api/images')
+      if (!response.ok) throw new Error(`HTTP ${response.status}`)
       const data = await response.json()
 
       const totalImages = data.images.length
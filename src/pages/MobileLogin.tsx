@@ -0,0 +1,73 @@
+import { useState } from 'react'
+import { Navigate, useLocation, useNavigate } from 'react-router-dom'
+import { useAuthStore } from '@/stores/authStore'
+import { login } from '@/utils/api'
+
+export function MobileLogin() {
+  const navigate = useNavigate()
+  const location = useLocation()
+  const isAuthenticated = useAuthStore((state) => state.isAuthenticated)
+  const [password, setPassword] = useState('')
+  const [isLoading, setIsLoading] = useState(false)
+  const [error, setError] = useState('')
+
+  // 登录后回到跳转前的页面
+  const from = (location.state as { from?: string } | null)?.from || '/'
+
+  if (isAuthenticated) {
+    return <Navigate to={from} replace />
+  }
+
+  const handleLogin = async (e: React.FormEvent) => {
+    e.preventDefault()
+    setIsLoading(true)
+    setError('')
+
+    try {
+      await login(password)
+      navigate(from, { replace: true })
+    } catch (err: any) {
+      setError(err.message || '登录失败，请检查密码')
+    } finally {
+      setIsLoading(false)
+    }
+  }
+
+  return (
+    <div className="min-h-screen bg-gradient-to-b from-background via-background to-gold/5 flex items-center justify-center p-4">
+      <div className="w-full max-w-sm p-6 rounded-xl bg-card border border-border space-y-4">
+        <div>
+          <h1 className="text-2xl font-bold text-foreground">登录</h1>
+          <p className="text-sm text-muted-foreground mt-1">
+            输入管理员密码，或在电脑上打开二维码页面扫码登录
+          </p>
+        </div>
+
+        {error && (
+          <div className="p-3 rounded-lg bg-red-500/10 text-red-500 text-sm">
+            {error}
+          </div>
+        )}
+
+        <form onSubmit={handleLogin} className="space-y-3">
+          <input
+            type="password"
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            placeholder="输入密码..."
+            className="w-full px-3 py-2 bg-background border border-border rounded-lg text-foreground placeholder:text-muted-foreground focus:outline-none focus:ring-2 focus:ring-gold"
+            disabled={isLoading}
+            autoFocus
+          />
+          <button
+            type="submit"
+            disabled={!password.trim() || isLoading}
+            className="w-full px-4 py-2.5 bg-gold text-background rounded-xl font-medium hover:bg-gold/90 transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {isLoading ? '登录中...' : '登录'}
+          </button>
+        </form>
+      </div>
+    </div>
+  )
+}
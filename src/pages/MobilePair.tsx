@@ -0,0 +1,56 @@
+import { useEffect, useRef, useState } from 'react'
+import { Link, useNavigate, useSearchParams } from 'react-router-dom'
+import { redeemPairingToken } from '@/utils/api'
+
+export function MobilePair() {
+  const navigate = useNavigate()
+  const [searchParams] = useSearchParams()
+  const [error, setError] = useState('')
+  // 配对令牌只能使用一次，避免 StrictMode 下重复提交
+  const redeemed = useRef(false)
+
+  useEffect(() => {
+    if (redeemed.current) return
+    redeemed.current = true
+
+    const token = searchParams.get('token')
+    if (!token) {
+      setError('链接中缺少配对码')
+      return
+    }
+
+    redeemPairingToken(token)
+      .then(() => navigate('/', { replace: true }))
+      .catch((err: any) => setError(err.message || '配对失败'))
+  }, [searchParams, navigate])
+
+  return (
+    <div className="min-h-screen bg-gradient-to-b from-background via-background to-gold/5 flex items-center justify-center p-4">
+      <div className="w-full max-w-sm p-6 rounded-xl bg-card border border-border space-y-4 text-center">
+        {error ? (
+          <>
+            <h1 className="text-2xl font-bold text-foreground">配对失败</h1>
+            <div className="p-3 rounded-lg bg-red-500/10 text-red-500 text-sm">
+              {error}
+            </div>
+            <p className="text-sm text-muted-foreground">
+              请在电脑上刷新二维码后重新扫描，或使用密码登录
+            </p>
+            <Link
+              to="/login"
+              replace
+              className="block w-full px-4 py-2.5 bg-gold text-background rounded-xl font-medium hover:bg-gold/90 transition-colors"
+            >
+              使用密码登录
+            </Link>
+          </>
+        ) : (
+          <>
+            <div className="w-12 h-12 mx-auto border-4 border-gold border-t-transparent rounded-full animate-spin" />
+            <p className="text-muted-foreground">正在配对...</p>
+          </>
+        )}
+      </div>
+    </div>
+  )
+}
@@ -1,10 +1,12 @@
 import { useState } from 'react'
 import { useNavigate, useLocation } from 'react-router-dom'
+import { useAuthStore } from '@/stores/authStore'
 
 export function MobileSettings() {
   const navigate = useNavigate()
   const location = useLocation()
   const [serverUrl, setServerUrl] = useState(() => window.location.origin)
+  const clearToken = useAuthStore((state) => state.clearToken)
 
   return (
     <div className="min-h-screen bg-gradient-to-b from-background via-background to-gold/5 pb-20">
@@ -50,6 +52,17 @@ export function MobileSettings() {
           </div>
         </div>
 
+        {/* 账号 */}
+        <div className="p-4 rounded-xl bg-card border border-border space-y-3">
+          <h2 className="text-sm font-semibold text-muted-foreground">账号</h2>
+          <button
+            onClick={clearToken}
+            className="w-full px-4 py-2 bg-red-500/10 text-red-500 rounded-lg text-sm font-medium hover:bg-red-500/20 transition-colors"
+          >
+            退出登录
+          </button>
+        </div>
+
         {/* 关于 */}
         <div className="p-4 rounded-xl bg-card border border-border space-y-3">
           <h2 className="text-sm font-semibold text-muted-foreground">关于</h2>
@@ -1,5 +1,15 @@
 import { useState, useCallback, useRef } from 'react'
 import { useNavigate, useLocation } from 'react-router-dom'
+import { apiFetch } from '@/utils/api'
+
+// POST /api/images 返回的单个文件处理结果
+interface UploadFileResult {
+  filename: string
+  success: boolean
+  message: string
+  image_id: number | null
+  file_size: number | null
+}
 
 interface UploadProgress {
   file: File
@@ -11,7 +21,6 @@ interface UploadProgress {
 export function MobileUpload() {
   const navigate = useNavigate()
   const location = useLocation()
-  const [serverUrl] = useState(() => window.location.origin)
   const fileInputRef = useRef<HTMLInputElement>(null)
   const [isDragging, setIsDragging] = useState(false)
   const [uploads, setUploads] = useState<UploadProgress[]>([])
@@ -30,16 +39,20 @@ export function MobileUpload() {
   }, [])
 
   const uploadFile = async (upload: UploadProgress): Promise<void> => {
+    // 服务器接受任意字段名的文件字段，每个文件单独请求以便分别显示状态
     const formData = new FormData()
-    formData.append('file', upload.file)
+    formData.append('files', upload.file)
 
     try {
-      const response = await fetch(`${serverUrl}/api/upload`, {
+      const response = await apiFetch('/api/images', {
         method: 'POST',
         body: formData,
       })
 
-      if (!response.ok) throw new Error('上传失败')
+      const data = await response.json().catch(() => ({}))
+      const result: UploadFileResult | undefined = data.results?.[0]
+      if (!response.ok || !result) throw new Error(data.error || '上传失败')
+      if (!result.success) throw new Error(result.message || '上传失败')
 
       setUploads(prev =>
         prev.map(u =>
@@ -1,5 +1,4 @@
 import { useState, useEffect } from 'react'
-import { QRCodeSVG } from 'qrcode.react'
 
 interface NetworkInfo {
   ipAddress: string
@@ -9,6 +8,17 @@ interface NetworkInfo {
   hostname?: string
 }
 
+// 一次性配对码，二维码中编码的是 pairing_url
+interface PairingCode {
+  server_url: string
+  token: string
+  expires_at: number
+  pairing_url: string
+  qr_code: string
+}
+
+const nowSecs = () => Math.floor(Date.now() / 1000)
+
 export function QrCode() {
   const [networkInfo, setNetworkInfo] = useState<NetworkInfo | null>(null)
   const [loading, setLoading] = useState(true)
@@ -17,14 +27,37 @@ export function QrCode() {
   const [serverRunning, setServerRunning] = useState(false)
   const [serverLoading, setServerLoading] = useState(false)
   const [copied, setCopied] = useState(false)
+  const [pairing, setPairing] = useState<PairingCode | null>(null)
+  const [now, setNow] = useState(nowSecs)
 
   useEffect(() => {
     setMounted(true)
     checkServerStatus()
     const interval = setInterval(checkServerStatus, 5000)
-    return () => clearInterval(interval)
+    const clock = setInterval(() => setNow(nowSecs()), 1000)
+    return () => {
+      clearInterval(interval)
+      clearInterval(clock)
+    }
   }, [])
 
+  // 服务器启动后生成配对码，停止后清除
+  useEffect(() => {
+    if (serverRunning) {
+      fetchPairingCode()
+    } else {
+      setPairing(null)
+    }
+  }, [serverRunning])
+
+  // 配对码过期后自动换一个新的
+  useEffect(() => {
+    if (serverRunning && pairing && now >= pairing.expires_at) {
+      setPairing(null)
+      fetchPairingCode()
+    }
+  }, [now, pairing, serverRunning])
+
   const checkServerStatus = async () => {
     try {
       const response = await fetch('http://localhost:3000/api/health')
@@ -45,11 +78,8 @@ export function QrCode() {
 
   const fetchNetworkInfo = async () => {
     try {
-      const response = await fetch('http://localhost:3000/api/network')
-      if (!response.ok) {
-        throw new Error('获取网络信息失败')
-      }
-      const data = await response.json()
+      const { invoke } = await import('@tauri-apps/api/core')
+      const data = await invoke<NetworkInfo>('get_network_info')
       setNetworkInfo(data)
     } catch (err) {
       setError(err instanceof Error ? err.message : '未知错误')
@@ -58,6 +88,21 @@ export function QrCode() {
     }
   }
 
+  const fetchPairingCode = async () => {
+    try {
+      const { invoke } = await import('@tauri-apps/api/core')
+      const data = await invoke<PairingCode>('create_pairing_code', { format: 'svg' })
+      setPairing(data)
+    } catch (err) {
+      setError('生成配对码失败: ' + (err instanceof Error ? err.message : String(err)))
+    }
+  }
+
+  const refresh = async () => {
+    await fetchNetworkInfo()
+    await fetchPairingCode()
+  }
+
   const startServer = async () => {
     setServerLoading(true)
     try {
@@ -186,16 +231,28 @@ export function QrCode() {
             {/* 二维码 */}
             <div className="flex flex-col items-center justify-center p-8 rounded-xl bg-card border border-border gradient-border">
               <div className="bg-white p-6 rounded-xl shadow-2xl">
-                <QRCodeSVG
-                  value={networkInfo.url}
-                  size={280}
-                  level="M"
-                  includeMargin={true}
-                />
+                {pairing ? (
+                  <img src={pairing.qr_code} alt="配对二维码" width={280} height={280} />
+                ) : (
+                  <div className="w-[280px] h-[280px] flex items-center justify-center">
+                    <div className="w-12 h-12 border-4 border-gold border-t-transparent rounded-full animate-spin"></div>
+                  </div>
+                )}
               </div>
               <p className="text-sm text-muted-foreground mt-4 text-center">
-                使用手机扫描二维码
+                使用手机扫描二维码，无需输入密码即可登录
               </p>
+              {pairing && (
+                <p className="text-xs text-muted-foreground mt-1 text-center">
+                  二维码仅可使用一次，{Math.max(pairing.expires_at - now, 0)} 秒后自动更新
+                </p>
+              )}
+              <button
+                onClick={fetchPairingCode}
+                className="mt-3 text-xs px-3 py-1.5 bg-card border border-border rounded-lg hover:border-gold/50 transition-colors"
+              >
+                换一个二维码
+              </button>
             </div>
 
             {/* 访问信息 */}
@@ -264,8 +321,8 @@ export function QrCode() {
         <div className="grid grid-cols-1 md:grid-cols-4 gap-4">
           {[
             { icon: '📱', title: '扫描二维码', desc: '使用手机相机扫描上方二维码' },
-            { icon: '🌐', title: '打开链接', desc: '在手机浏览器中自动打开访问地址' },
-            { icon: '🔑', title: '登录系统', desc: '首次访问需要登录（默认密码: admin）' },
+            { icon: '🌐', title: '打开链接', desc: '在手机浏览器中自动打开配对页面' },
+            { icon: '🔑', title: '自动登录', desc: '配对后自动登录；直接访问地址时需要输入密码' },
             { icon: '📷', title: '浏览图片', desc: '可以在移动端浏览、搜索、上传图片' },
           ].map((step, index) => (
             <div
@@ -317,7 +374,7 @@ export function QrCode() {
             {copied ? '✅ 已复制' : '📋 复制访问地址'}
           </button>
           <button
-            onClick={fetchNetworkInfo}
+            onClick={refresh}
             className="px-6 py-3 bg-card border border-border rounded-lg hover:border-gold/50 transition-colors flex items-center gap-2"
           >
             🔄 刷新
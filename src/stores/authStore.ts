@@ -1,24 +1,52 @@
 import { create } from 'zustand'
 
+// 登录令牌保存在 localStorage 中，刷新页面后仍然有效
+const TOKEN_KEY = 'apiToken'
+const EXPIRES_KEY = 'apiTokenExpiresAt'
+
 interface AuthState {
   token: string | null
+  // 令牌过期时间（Unix 秒）
+  expiresAt: number | null
   isAuthenticated: boolean
   user: string | null
 }
 
 interface AuthStore extends AuthState {
-  setToken: (token: string) => void
+  setToken: (token: string, expiresAt?: number) => void
   clearToken: () => void
   setUser: (user: string) => void
   clearUser: () => void
 }
 
+function loadStoredToken(): Pick<AuthState, 'token' | 'expiresAt' | 'isAuthenticated'> {
+  const token = localStorage.getItem(TOKEN_KEY)
+  const expiresAt = Number(localStorage.getItem(EXPIRES_KEY)) || null
+  if (!token || (expiresAt !== null && expiresAt * 1000 <= Date.now())) {
+    localStorage.removeItem(TOKEN_KEY)
+    localStorage.removeItem(EXPIRES_KEY)
+    return { token: null, expiresAt: null, isAuthenticated: false }
+  }
+  return { token, expiresAt, isAuthenticated: true }
+}
+
 export const useAuthStore = create<AuthStore>((set) => ({
-  token: null,
-  isAuthenticated: false,
+  ...loadStoredToken(),
   user: null,
-  setToken: (token) => set({ token, isAuthenticated: !!token }),
-  clearToken: () => set({ token: null, isAuthenticated: false }),
+  setToken: (token, expiresAt) => {
+    localStorage.setItem(TOKEN_KEY, token)
+    if (expiresAt) {
+      localStorage.setItem(EXPIRES_KEY, String(expiresAt))
+    } else {
+      localStorage.removeItem(EXPIRES_KEY)
+    }
+    set({ token, expiresAt: expiresAt ?? null, isAuthenticated: !!token })
+  },
+  clearToken: () => {
+    localStorage.removeItem(TOKEN_KEY)
+    localStorage.removeItem(EXPIRES_KEY)
+    set({ token: null, expiresAt: null, isAuthenticated: false })
+  },
   setUser: (user) => set({ user }),
   clearUser: () => set({ user: null }),
 }))
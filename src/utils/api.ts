@@ -0,0 +1,114 @@
+import { useEffect, useState } from 'react'
+import { useAuthStore } from '@/stores/authStore'
+
+// 登录、配对和媒体令牌接口的返回值
+export interface TokenResponse {
+  token: string
+  expires_at: number
+}
+
+// 媒体令牌在过期前多久刷新（毫秒）
+const MEDIA_TOKEN_REFRESH_MARGIN = 30_000
+
+// 获取媒体令牌失败后的重试间隔（毫秒）
+const MEDIA_TOKEN_RETRY_DELAY = 10_000
+
+// 网页端与 API 服务器同源
+export const serverUrl = () => window.location.origin
+
+/**
+ * 带登录令牌请求 API（path 以 /api 开头）
+ * 服务器返回 401 时清除本地令牌，页面随之跳转到登录页
+ */
+export async function apiFetch(path: string, init: RequestInit = {}): Promise<Response> {
+  const { token, clearToken } = useAuthStore.getState()
+  const headers = new Headers(init.headers)
+  if (token) {
+    headers.set('Authorization', `Bearer ${token}`)
+  }
+
+  const response = await fetch(`${serverUrl()}${path}`, { ...init, headers })
+  if (response.status === 401 && token) {
+    clearToken()
+  }
+  return response
+}
+
+async function requestToken(path: string, body: unknown): Promise<void> {
+  const response = await fetch(`${serverUrl()}${path}`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(body),
+  })
+  const data = await response.json().catch(() => ({}))
+  if (!response.ok) {
+    throw new Error(data.error || '登录失败')
+  }
+
+  const { token, expires_at } = data as TokenResponse
+  useAuthStore.getState().setToken(token, expires_at)
+}
+
+// 用密码登录
+export function login(password: string): Promise<void> {
+  return requestToken('/api/login', { password })
+}
+
+// 用扫码得到的一次性配对令牌登录
+export function redeemPairingToken(token: string): Promise<void> {
+  return requestToken('/api/pair', { token })
+}
+
+/**
+ * 获取用于 <img src> 的短期媒体令牌，过期前自动刷新
+ * 未登录或令牌尚未获取时返回 null
+ */
+export function useMediaToken(): string | null {
+  const token = useAuthStore((state) => state.token)
+  const [mediaToken, setMediaToken] = useState<string | null>(null)
+
+  useEffect(() => {
+    if (!token) {
+      setMediaToken(null)
+      return
+    }
+
+    let cancelled = false
+    let timer: number | undefined
+
+    const refresh = async () => {
+      try {
+        const response = await apiFetch('/api/media-token', { method: 'POST' })
+        if (!response.ok) throw new Error(`HTTP ${response.status}`)
+        const data: TokenResponse = await response.json()
+        if (cancelled) return
+
+        setMediaToken(data.token)
+        const delay = Math.max(
+          data.expires_at * 1000 - Date.now() - MEDIA_TOKEN_REFRESH_MARGIN,
+          MEDIA_TOKEN_RETRY_DELAY,
+        )
+        timer = window.setTimeout(refresh, delay)
+      } catch (error) {
+        console.error('获取媒体令牌失败:', error)
+        if (!cancelled) {
+          timer = window.setTimeout(refresh, MEDIA_TOKEN_RETRY_DELAY)
+        }
+      }
+    }
+
+    refresh()
+    return () => {
+      cancelled = true
+      window.clearTimeout(timer)
+    }
+  }, [token])
+
+  return mediaToken
+}
+
+// 图片文件或缩略图地址，媒体令牌放在查询参数中
+export function mediaUrl(id: number, kind: 'file' | 'thumbnail', mediaToken: string | null): string | undefined {
+  if (!mediaToken) return undefined
+  return `${serverUrl()}/api/images/${id}/${kind}?token=${encodeURIComponent(mediaToken)}`
+}